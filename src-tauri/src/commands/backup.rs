@@ -0,0 +1,34 @@
+use crate::commands::{cmd_result::CmdResult, storage};
+use crate::services::backup::{BackupInfo, Backups};
+use crate::services::stores::file::STORE_FILE;
+use anyhow::Context;
+use tauri::AppHandle;
+
+/// Lists the backups, newest first
+#[tauri::command]
+pub fn list_backups(app_handle: AppHandle) -> CmdResult<Vec<BackupInfo>> {
+    Ok(Backups::new(&app_handle)
+        .list()
+        .context("Failed to list backups")?)
+}
+
+/// Replaces the library, history and settings with a backup.
+/// The current data is backed up first so the restore can be undone.
+/// Returns a notice to show when part of the backup couldn't be applied.
+#[tauri::command]
+pub fn restore_backup(app_handle: AppHandle, name: String) -> CmdResult<Option<String>> {
+    let restored = Backups::new(&app_handle)
+        .restore(&name)
+        .context("Failed to restore backup")?;
+
+    let mut notice = None;
+    for &file in &restored {
+        notice = notice.or(storage::reload_store_file(&app_handle, file)?);
+    }
+
+    // Reloading store.json already brought the games in line with the restored database
+    if !restored.contains(&STORE_FILE) {
+        storage::reload_games(&app_handle)?;
+    }
+    Ok(notice)
+}
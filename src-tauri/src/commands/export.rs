@@ -0,0 +1,54 @@
+use crate::commands::cmd_result::CmdResult;
+use crate::services::export::{self, ExportFormat, ExportOptions};
+use crate::services::stores::{games::GamesStore, sessions::SessionsStore};
+use anyhow::Context;
+use log::info;
+use std::fs;
+use tauri::AppHandle;
+
+/// Writes the library to `path` as CSV or JSON
+#[tauri::command]
+pub fn export_games(
+    app_handle: AppHandle,
+    format: ExportFormat,
+    options: ExportOptions,
+    path: String,
+) -> CmdResult<()> {
+    info!("Exporting library as {:?} to {}", format, path);
+    let store = GamesStore::new(&app_handle).context("Error happened while accessing store")?;
+    let games = store
+        .get_all()
+        .context("Error happened while getting games")?;
+
+    let records = export::game_records(&games, &options);
+    let content = export::render(records, format).context("Failed to render library")?;
+    fs::write(&path, content).context(format!("Failed to write export to {}", path))?;
+    Ok(())
+}
+
+/// Writes the session history to `path` as CSV or JSON
+#[tauri::command]
+pub fn export_sessions(
+    app_handle: AppHandle,
+    format: ExportFormat,
+    options: ExportOptions,
+    path: String,
+) -> CmdResult<()> {
+    info!("Exporting sessions as {:?} to {}", format, path);
+    let games_store =
+        GamesStore::new(&app_handle).context("Error happened while accessing store")?;
+    let sessions_store =
+        SessionsStore::new(&app_handle).context("Error happened while accessing store")?;
+
+    let games = games_store
+        .get_all()
+        .context("Error happened while getting games")?;
+    let sessions = sessions_store
+        .get_all()
+        .context("Error happened while getting sessions")?;
+
+    let records = export::session_records(&games, &sessions, &options);
+    let content = export::render(records, format).context("Failed to render sessions")?;
+    fs::write(&path, content).context(format!("Failed to write export to {}", path))?;
+    Ok(())
+}
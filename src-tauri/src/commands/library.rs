@@ -0,0 +1,43 @@
+use crate::commands::{cmd_result::CmdResult, storage};
+use crate::services::library::{ImportOptions, ImportPreview, LibraryArchive, LibraryManifest};
+use crate::services::stores::file::SETTINGS_FILE;
+use anyhow::Context;
+use std::path::Path;
+use tauri::AppHandle;
+
+/// Writes the whole library, history, settings and cover images to an archive at `path`
+#[tauri::command]
+pub fn export_library(app_handle: AppHandle, path: String) -> CmdResult<LibraryManifest> {
+    Ok(LibraryArchive::new(&app_handle)
+        .export(Path::new(&path))
+        .context("Failed to export library")?)
+}
+
+/// Shows what importing the archive at `path` would change
+#[tauri::command]
+pub fn preview_library_import(
+    app_handle: AppHandle,
+    path: String,
+    options: ImportOptions,
+) -> CmdResult<ImportPreview> {
+    Ok(LibraryArchive::new(&app_handle)
+        .preview(Path::new(&path), &options)
+        .context("Failed to read library archive")?)
+}
+
+/// Imports the archive at `path`, the current data is backed up first
+#[tauri::command]
+pub fn import_library(
+    app_handle: AppHandle,
+    path: String,
+    options: ImportOptions,
+) -> CmdResult<ImportPreview> {
+    let imported = LibraryArchive::new(&app_handle)
+        .import(Path::new(&path), &options)
+        .context("Failed to import library")?;
+
+    if imported.settings {
+        storage::reload_store_file(&app_handle, SETTINGS_FILE)?;
+    }
+    Ok(imported)
+}
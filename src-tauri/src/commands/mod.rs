@@ -1,5 +1,10 @@
+pub mod backup;
 mod cmd_result;
+pub mod export;
 pub mod jiten;
+pub mod library;
 pub mod opener;
+pub mod sessions;
+pub mod stats;
 pub mod storage;
 pub mod vndb;
@@ -2,7 +2,8 @@ use crate::{
     commands::cmd_result::CmdResult,
     services::{
         game_manager::GameManager,
-        system::{ActiveWindow, SystemService},
+        stores::games::ProcessMatcher,
+        system::{ActiveWindow, CompiledMatcher, MatchedProcess, SystemService},
     },
 };
 use anyhow::Context;
@@ -23,11 +24,40 @@ pub fn get_active_windows() -> CmdResult<Vec<ActiveWindow>> {
     Ok(SystemService::get_active_windows()?)
 }
 
-/// Closes a game and clears local state
+/// Lists the running processes a process matcher would accept
 #[tauri::command]
-pub fn close_game(app_handle: AppHandle) -> CmdResult<()> {
+pub fn test_process_matcher(
+    process_file_path: String,
+    matcher: ProcessMatcher,
+) -> CmdResult<Vec<MatchedProcess>> {
+    let matcher =
+        CompiledMatcher::new(&matcher, &process_file_path).context("Invalid process matcher")?;
+    Ok(SystemService::list_matching_processes(&matcher))
+}
+
+/// Stops crediting playtime to a running game until resumed
+#[tauri::command]
+pub fn pause_tracking(app_handle: AppHandle, game_id: String) -> CmdResult<()> {
     GameManager::new(&app_handle)
-        .close()
-        .context("Failed to open game")?;
+        .set_paused(&game_id, true)
+        .context("Failed to pause tracking")?;
+    Ok(())
+}
+
+/// Resumes crediting playtime to a paused game
+#[tauri::command]
+pub fn resume_tracking(app_handle: AppHandle, game_id: String) -> CmdResult<()> {
+    GameManager::new(&app_handle)
+        .set_paused(&game_id, false)
+        .context("Failed to resume tracking")?;
+    Ok(())
+}
+
+/// Closes a running game, or every running game when no id is given
+#[tauri::command]
+pub fn close_game(app_handle: AppHandle, game_id: Option<String>) -> CmdResult<()> {
+    GameManager::new(&app_handle)
+        .close(game_id.as_deref())
+        .context("Failed to close game")?;
     Ok(())
 }
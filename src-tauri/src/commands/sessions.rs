@@ -0,0 +1,83 @@
+use crate::commands::cmd_result::CmdResult;
+use crate::services::session_manager::{ManualSessionInput, SessionManager};
+use crate::services::stores::sessions::{PlaySession, Sessions, SessionsStore};
+use anyhow::Context;
+use log::{debug, info};
+use tauri::AppHandle;
+
+/// Gets all play sessions, optionally only those of a single game
+#[tauri::command]
+pub fn get_sessions(app_handle: AppHandle, game_id: Option<String>) -> CmdResult<Sessions> {
+    debug!("Getting sessions for game: {:?}", game_id);
+    let store = SessionsStore::new(&app_handle).context("Error happened while accessing store")?;
+
+    let sessions = match game_id {
+        Some(id) => store.get_by_game(&id),
+        None => store.get_all(),
+    }
+    .context("Error happened while getting sessions")?;
+
+    debug!("Successfully loaded {} sessions", sessions.len());
+    Ok(sessions)
+}
+
+/// Gets all play sessions overlapping the given range of Unix timestamps (seconds)
+#[tauri::command]
+pub fn get_sessions_in_range(app_handle: AppHandle, from: u64, to: u64) -> CmdResult<Sessions> {
+    debug!("Getting sessions between {} and {}", from, to);
+    let store = SessionsStore::new(&app_handle).context("Error happened while accessing store")?;
+
+    let sessions = store
+        .get_in_range(from, to)
+        .context("Error happened while getting sessions")?;
+
+    debug!("Successfully loaded {} sessions", sessions.len());
+    Ok(sessions)
+}
+
+/// Logs a session for reading Tadoku couldn't track, crediting it to the game
+#[tauri::command]
+pub fn create_manual_session(
+    app_handle: AppHandle,
+    game_id: String,
+    session: ManualSessionInput,
+) -> CmdResult<PlaySession> {
+    info!("Creating manual session for game: {}", game_id);
+
+    let session = SessionManager::new(&app_handle)
+        .create_manual(&game_id, session)
+        .context("Error happened while creating manual session")?;
+
+    info!("Successfully created manual session: {}", session.id);
+    Ok(session)
+}
+
+/// Edits a manual session, updating the game's stats to match
+#[tauri::command]
+pub fn update_manual_session(
+    app_handle: AppHandle,
+    session_id: String,
+    session: ManualSessionInput,
+) -> CmdResult<PlaySession> {
+    info!("Updating manual session: {}", session_id);
+
+    let session = SessionManager::new(&app_handle)
+        .update_manual(&session_id, session)
+        .context("Error happened while updating manual session")?;
+
+    info!("Successfully updated manual session: {}", session_id);
+    Ok(session)
+}
+
+/// Deletes a play session, manual sessions are also taken back from the game's stats
+#[tauri::command]
+pub fn delete_session(app_handle: AppHandle, session_id: String) -> CmdResult<()> {
+    info!("Deleting session: {}", session_id);
+
+    SessionManager::new(&app_handle)
+        .delete(&session_id)
+        .context("Error happened while deleting session")?;
+
+    info!("Successfully deleted session: {}", session_id);
+    Ok(())
+}
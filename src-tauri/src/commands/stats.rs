@@ -0,0 +1,150 @@
+use crate::commands::cmd_result::CmdResult;
+use crate::services::goals::{GoalProgress, GoalTracker};
+use crate::services::report::{Report, ReportFormat};
+use crate::services::state::ManagedState;
+use crate::services::stats::{self, Period, ReadingSpeed, Stats};
+use crate::services::stores::{
+    games::{DailyTotals, Games, GamesRepository, GamesStore},
+    sessions::{Sessions, SessionsStore},
+};
+use crate::util::{self, DayBoundary};
+use anyhow::Context;
+use chrono::NaiveDate;
+use log::{debug, info};
+use std::fs;
+use tauri::{AppHandle, Manager};
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Gets playtime and chars read per day across the whole library
+#[tauri::command]
+pub fn get_daily_totals(app_handle: AppHandle) -> CmdResult<DailyTotals> {
+    debug!("Getting daily totals");
+    let store = GamesStore::new(&app_handle).context("Error happened while accessing store")?;
+
+    let totals = store
+        .get_daily_totals()
+        .context("Error happened while getting daily totals")?;
+
+    debug!("Successfully loaded totals for {} days", totals.len());
+    Ok(totals)
+}
+
+/// Gets reading speed (characters per hour) overall, per day, per game and per session,
+/// optionally for a single game
+#[tauri::command]
+pub fn get_reading_speed(
+    app_handle: AppHandle,
+    game_id: Option<String>,
+) -> CmdResult<ReadingSpeed> {
+    debug!("Getting reading speed for game: {:?}", game_id);
+    let sessions_store =
+        SessionsStore::new(&app_handle).context("Error happened while accessing store")?;
+
+    let sessions = match &game_id {
+        Some(id) => sessions_store.get_by_game(id),
+        None => sessions_store.get_all(),
+    }
+    .context("Error happened while getting sessions")?;
+
+    Ok(app_handle
+        .state::<GamesRepository>()
+        .read(|games| match &game_id {
+            Some(id) => {
+                let game: Games = games
+                    .get(id)
+                    .map(|game| (id.clone(), game.clone()))
+                    .into_iter()
+                    .collect();
+                stats::reading_speed(&game, &sessions)
+            }
+            None => stats::reading_speed(games, &sessions),
+        })
+        .context("Error happened while getting games")?)
+}
+
+/// Gets library-wide statistics over an inclusive range of reading days (`YYYY-MM-DD`),
+/// open ended when a bound is omitted
+#[tauri::command]
+pub fn get_stats(
+    app_handle: AppHandle,
+    from: Option<String>,
+    to: Option<String>,
+) -> CmdResult<Stats> {
+    debug!("Getting stats from {:?} to {:?}", from, to);
+    let (day, sessions) = load_period(&app_handle, &from, &to)?;
+
+    let period = Period {
+        from: from.as_deref(),
+        to: to.as_deref(),
+    };
+    Ok(app_handle
+        .state::<GamesRepository>()
+        .read(|games| stats::stats(games, &sessions, &day, &period))
+        .context("Error happened while getting games")?)
+}
+
+/// Renders a reading report over an inclusive range of reading days (`YYYY-MM-DD`)
+/// and writes it to `path`
+#[tauri::command]
+pub fn export_report(
+    app_handle: AppHandle,
+    from: Option<String>,
+    to: Option<String>,
+    format: ReportFormat,
+    path: String,
+) -> CmdResult<()> {
+    info!("Exporting {:?} report from {:?} to {:?}", format, from, to);
+    let (day, sessions) = load_period(&app_handle, &from, &to)?;
+    // Loaded through the store for the cover paths
+    let games = GamesStore::new(&app_handle)
+        .and_then(|store| store.get_all())
+        .context("Error happened while getting games")?;
+
+    let period = Period {
+        from: from.as_deref(),
+        to: to.as_deref(),
+    };
+    let report = Report::new(&games, &sessions, &day, &period);
+
+    fs::write(&path, report.render(format))
+        .context(format!("Failed to write report to {}", path))?;
+    Ok(())
+}
+
+/// Validates the period's bounds and loads the day boundary and the sessions around the period
+fn load_period(
+    app_handle: &AppHandle,
+    from: &Option<String>,
+    to: &Option<String>,
+) -> CmdResult<(DayBoundary, Sessions)> {
+    let parse = |date: &String| {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").context(format!("Invalid date: {}", date))
+    };
+    let from = from.as_ref().map(parse).transpose()?;
+    let to = to.as_ref().map(parse).transpose()?;
+
+    let day = app_handle
+        .state::<ManagedState>()
+        .lock()?
+        .settings
+        .day_boundary();
+    let sessions_store =
+        SessionsStore::new(app_handle).context("Error happened while accessing store")?;
+
+    // A day of slack on both ends, sessions are matched to reading days when aggregating
+    let start = from.map_or(0, |date| day.start_of(date).saturating_sub(SECS_PER_DAY));
+    let end = to.map_or_else(util::unix_now, |date| day.start_of(date) + 2 * SECS_PER_DAY);
+    let sessions = sessions_store
+        .get_in_range(start, end)
+        .context("Error happened while getting sessions")?;
+
+    Ok((day, sessions))
+}
+
+/// Gets the progress of every reading goal in its current period
+#[tauri::command]
+pub fn get_goal_progress(app_handle: AppHandle) -> CmdResult<Vec<GoalProgress>> {
+    debug!("Getting reading goal progress");
+    Ok(GoalTracker::progress(&app_handle).context("Error happened while evaluating goals")?)
+}
@@ -1,18 +1,47 @@
 use crate::commands::cmd_result::CmdResult;
 use crate::services::game_saver::{GameSaver, Options};
 use crate::services::state::ManagedState;
+use crate::services::stats;
+use crate::services::system::CompiledMatcher;
 use crate::services::{
     discord::DiscordPresenceMode,
     stores::{
+        audit::{AuditEntry, AuditKind, AuditLog, AuditStore},
         categories::{Categories, CategoriesStore},
-        games::{Game, Games, GamesStore},
-        settings::{PlaytimeMode, SortOrder, ThemeSettings},
+        database::Database,
+        file::{CorruptStore, SETTINGS_FILE, STORE_FILE, StoreRecovery},
+        games::{Game, Games, GamesRepository, GamesStore, ProcessMatcher},
+        sessions::SessionsStore,
+        settings::{
+            BackupSettings, ConcurrentPlaytimePolicy, PlaytimeMode, ReadingGoal, SettingsStore,
+            SortOrder, ThemeSettings,
+        },
     },
 };
+use crate::setup;
+use crate::util::DayBoundary;
 use anyhow::Context;
+use chrono::NaiveDate;
 use log::{debug, info};
+use serde::Deserialize;
+use std::collections::HashSet;
 use tauri::{AppHandle, Manager};
 
+/// Longest idle timeout accepted, a day
+const MAX_IDLE_TIMEOUT_MINS: u64 = 24 * 60;
+
+/// A correction of a game's totals, negative values take time or characters back
+#[derive(Deserialize, Debug)]
+pub struct PlaytimeAdjustment {
+    #[serde(default)]
+    pub playtime_secs: i64,
+    #[serde(default)]
+    pub chars_read: i64,
+    pub reason: String,
+    /// Reading day (`YYYY-MM-DD`) the correction belongs to, today when `None`
+    pub date: Option<String>,
+}
+
 /// Saves a game to the local storage.
 ///
 /// **NOTE**: The image is either downloaded from a remote URL or copied from a
@@ -38,9 +67,10 @@ pub fn load_games(app_handle: AppHandle) -> CmdResult<Games> {
     debug!("Loading all games from storage");
     let store = GamesStore::new(&app_handle).context("Error happened while accessing store")?;
 
-    let games_data = store
+    let mut games_data = store
         .get_all()
         .context("Error happened while getting games")?;
+    stats::fill_progress(&mut games_data);
 
     debug!(
         "Successfully loaded {} games from storage",
@@ -59,6 +89,14 @@ pub fn delete_game(app_handle: AppHandle, game_id: String) -> CmdResult<()> {
         .delete(&game_id)
         .context("Error happened while deleting game")?;
 
+    SessionsStore::new(&app_handle)
+        .and_then(|sessions| sessions.delete_by_game(&game_id))
+        .context("Error happened while deleting game sessions")?;
+
+    AuditStore::new(&app_handle)
+        .and_then(|audit| audit.delete_by_game(&game_id))
+        .context("Error happened while deleting game audit log")?;
+
     info!("Successfully deleted game: {}", game_id);
     Ok(())
 }
@@ -82,15 +120,144 @@ pub fn toggle_pin(app_handle: AppHandle, game_id: String) -> CmdResult<()> {
 pub fn reset_stats(app_handle: AppHandle, game_id: String) -> CmdResult<()> {
     debug!("Resetting stats for game {}", game_id);
     let store = GamesStore::new(&app_handle).context("Error happened while accessing store")?;
+    let before = store.get(&game_id).context("Game not found")?;
 
     store
         .reset_stats(&game_id)
         .context("Error happened while resetting stats")?;
 
+    if let Some(after) = store.get(&game_id) {
+        AuditStore::new(&app_handle)
+            .and_then(|audit| {
+                audit.record([AuditEntry::between(
+                    &game_id,
+                    AuditKind::Reset,
+                    &before,
+                    &after,
+                    None,
+                )])
+            })
+            .context("Error happened while recording reset")?;
+    }
+
     info!("Successfully reset stats for game: {}", game_id);
     Ok(())
 }
 
+/// Adds or takes back playtime and characters read, recording the change in the audit log
+#[tauri::command]
+pub fn adjust_playtime(
+    app_handle: AppHandle,
+    game_id: String,
+    adjustment: PlaytimeAdjustment,
+) -> CmdResult<Game> {
+    info!("Adjusting stats for game {}: {:?}", game_id, adjustment);
+
+    let reason = adjustment.reason.trim().to_string();
+    if reason.is_empty() {
+        return Err(anyhow::anyhow!("A reason is required for adjustments").into());
+    }
+    if adjustment.playtime_secs == 0 && adjustment.chars_read == 0 {
+        return Err(anyhow::anyhow!("Adjustment changes nothing").into());
+    }
+
+    let today = app_handle
+        .state::<ManagedState>()
+        .lock()?
+        .settings
+        .day_boundary()
+        .today();
+    let date = match adjustment.date {
+        Some(date) => {
+            // Formatted again so both are zero-padded `YYYY-MM-DD` and order as strings
+            let date = NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+                .context(format!("Invalid date: {}", date))?
+                .format("%Y-%m-%d")
+                .to_string();
+            if date > today {
+                return Err(anyhow::anyhow!("Can't adjust stats of a future day: {}", date).into());
+            }
+            date
+        }
+        None => today,
+    };
+
+    let store = GamesStore::new(&app_handle).context("Error happened while accessing store")?;
+    let before = store.get(&game_id).context("Game not found")?;
+
+    // Taking back more than the day holds would leave the totals out of step with the ledgers
+    let day_playtime = before
+        .daily_playtime
+        .get(&date)
+        .copied()
+        .unwrap_or_default();
+    if adjustment.playtime_secs < 0 && adjustment.playtime_secs.unsigned_abs() > day_playtime {
+        return Err(anyhow::anyhow!(
+            "Only {} seconds of playtime were recorded on {}",
+            day_playtime,
+            date
+        )
+        .into());
+    }
+    let day_chars = before.daily_chars.get(&date).copied().unwrap_or_default();
+    if adjustment.chars_read < 0 && adjustment.chars_read.unsigned_abs() > day_chars {
+        return Err(
+            anyhow::anyhow!("Only {} characters were recorded on {}", day_chars, date).into(),
+        );
+    }
+
+    store
+        .update_game(&game_id, |g| {
+            let playtime = adjustment.playtime_secs.unsigned_abs();
+            if adjustment.playtime_secs > 0 {
+                g.add_playtime_on(playtime, &date);
+            } else {
+                g.remove_playtime_on(playtime, &date);
+            }
+
+            let chars = adjustment.chars_read.unsigned_abs();
+            g.pin_exstatic_chars();
+            // No reading time goes with adjusted characters, they stay out of reading speed
+            if adjustment.chars_read > 0 {
+                g.add_untimed_chars_on(chars, &date);
+            } else {
+                g.remove_untimed_chars_on(chars, &date);
+            }
+        })
+        .context("Error happened while adjusting stats")?;
+
+    let after = store.get(&game_id).context("Game not found")?;
+    AuditStore::new(&app_handle)
+        .and_then(|audit| {
+            audit.record([AuditEntry::between(
+                &game_id,
+                AuditKind::Adjustment,
+                &before,
+                &after,
+                Some(reason),
+            )])
+        })
+        .context("Error happened while recording adjustment")?;
+
+    info!("Successfully adjusted stats for game: {}", game_id);
+    Ok(after)
+}
+
+/// Gets the audit log, optionally only the entries of a single game
+#[tauri::command]
+pub fn get_audit_log(app_handle: AppHandle, game_id: Option<String>) -> CmdResult<AuditLog> {
+    debug!("Getting audit log for game: {:?}", game_id);
+    let store = AuditStore::new(&app_handle).context("Error happened while accessing store")?;
+
+    let log = match game_id {
+        Some(id) => store.get_by_game(&id),
+        None => store.get_all(),
+    }
+    .context("Error happened while getting audit log")?;
+
+    Ok(log)
+}
+
 /// Updates the exe path of a game
 #[tauri::command]
 pub fn update_exe(app_handle: AppHandle, game_id: String, new_exe_path: String) -> CmdResult<()> {
@@ -126,6 +293,32 @@ pub fn update_process(
     Ok(())
 }
 
+/// Updates how the running process of a game is recognized
+#[tauri::command]
+pub fn update_process_matcher(
+    app_handle: AppHandle,
+    game_id: String,
+    matcher: ProcessMatcher,
+) -> CmdResult<()> {
+    info!(
+        "Updating process matcher for game {}: {:?}",
+        game_id, matcher
+    );
+    let store = GamesStore::new(&app_handle).context("Error happened while accessing store")?;
+    let game = store
+        .get(&game_id)
+        .context(format!("Game not found in store: {}", game_id))?;
+
+    CompiledMatcher::new(&matcher, &game.process_file_path).context("Invalid process matcher")?;
+
+    store
+        .update_game(&game_id, |g| g.process_matcher = matcher)
+        .context("Error happened while updating process matcher")?;
+
+    info!("Successfully updated process matcher for game: {}", game_id);
+    Ok(())
+}
+
 /// Saves game notes to disk
 #[tauri::command]
 pub fn set_game_notes(app_handle: AppHandle, game_id: String, notes: String) -> CmdResult<()> {
@@ -433,3 +626,277 @@ pub fn set_jiten_base_url(app_handle: AppHandle, url: String) -> CmdResult<()> {
         .context("Failed to update jiten base url")?;
     Ok(())
 }
+
+/// Gets the reading day boundary (start hour and optional fixed UTC offset)
+#[tauri::command]
+pub fn get_day_boundary(app_handle: AppHandle) -> CmdResult<DayBoundary> {
+    Ok(app_handle
+        .state::<ManagedState>()
+        .lock()?
+        .settings
+        .day_boundary())
+}
+
+/// Sets the reading day boundary
+#[tauri::command]
+pub fn set_day_boundary(app_handle: AppHandle, to: DayBoundary) -> CmdResult<()> {
+    if to.start_hour > 23 {
+        return Err(anyhow::anyhow!("Day start hour must be between 0 and 23").into());
+    }
+    if to.utc_offset_minutes.is_some_and(|m| m.abs() > 14 * 60) {
+        return Err(anyhow::anyhow!("UTC offset must be within +/-14 hours").into());
+    }
+
+    let state = app_handle.state::<ManagedState>();
+    let mut lock = state.lock()?;
+    lock.update_settings(&app_handle, |s| {
+        s.day_start_hour = to.start_hour;
+        s.utc_offset_minutes = to.utc_offset_minutes;
+    })
+    .context("Failed to update day boundary")?;
+    Ok(())
+}
+
+/// Gets whether games launched outside Tadoku are tracked automatically
+#[tauri::command]
+pub fn get_auto_detect_games(app_handle: AppHandle) -> CmdResult<bool> {
+    Ok(app_handle
+        .state::<ManagedState>()
+        .lock()?
+        .settings
+        .auto_detect_games)
+}
+
+/// Sets whether games launched outside Tadoku are tracked automatically
+#[tauri::command]
+pub fn set_auto_detect_games(app_handle: AppHandle, to: bool) -> CmdResult<()> {
+    let state = app_handle.state::<ManagedState>();
+    let mut lock = state.lock()?;
+    lock.update_settings(&app_handle, |s| s.auto_detect_games = to)
+        .context("Failed to update auto detect games")?;
+    Ok(())
+}
+
+/// Gets which running games accrue playtime when several are open
+#[tauri::command]
+pub fn get_concurrent_playtime_policy(
+    app_handle: AppHandle,
+) -> CmdResult<ConcurrentPlaytimePolicy> {
+    Ok(app_handle
+        .state::<ManagedState>()
+        .lock()?
+        .settings
+        .concurrent_playtime_policy)
+}
+
+/// Sets which running games accrue playtime when several are open
+#[tauri::command]
+pub fn set_concurrent_playtime_policy(
+    app_handle: AppHandle,
+    to: ConcurrentPlaytimePolicy,
+) -> CmdResult<()> {
+    let state = app_handle.state::<ManagedState>();
+    let mut lock = state.lock()?;
+    lock.update_settings(&app_handle, |s| s.concurrent_playtime_policy = to)
+        .context("Failed to update concurrent playtime policy")?;
+    Ok(())
+}
+
+/// Gets how many seconds to look for a game's process after launching it
+#[tauri::command]
+pub fn get_process_discovery_timeout(app_handle: AppHandle) -> CmdResult<u64> {
+    Ok(app_handle
+        .state::<ManagedState>()
+        .lock()?
+        .settings
+        .process_discovery_timeout_secs)
+}
+
+/// Sets how many seconds to look for a game's process after launching it
+#[tauri::command]
+pub fn set_process_discovery_timeout(app_handle: AppHandle, to: u64) -> CmdResult<()> {
+    let state = app_handle.state::<ManagedState>();
+    let mut lock = state.lock()?;
+    lock.update_settings(&app_handle, |s| s.process_discovery_timeout_secs = to)
+        .context("Failed to update process discovery timeout")?;
+    Ok(())
+}
+
+/// Gets the minutes without activity after which a game counts as idle
+#[tauri::command]
+pub fn get_idle_timeout(app_handle: AppHandle) -> CmdResult<u64> {
+    Ok(app_handle
+        .state::<ManagedState>()
+        .lock()?
+        .settings
+        .idle_timeout_mins)
+}
+
+/// Sets the minutes without activity after which a game counts as idle, 0 disables it
+#[tauri::command]
+pub fn set_idle_timeout(app_handle: AppHandle, to: u64) -> CmdResult<()> {
+    if to > MAX_IDLE_TIMEOUT_MINS {
+        return Err(anyhow::anyhow!(
+            "Idle timeout can't be longer than {} minutes",
+            MAX_IDLE_TIMEOUT_MINS
+        )
+        .into());
+    }
+
+    let state = app_handle.state::<ManagedState>();
+    let mut lock = state.lock()?;
+    lock.update_settings(&app_handle, |s| s.idle_timeout_mins = to)
+        .context("Failed to update idle timeout")?;
+    Ok(())
+}
+
+/// Gets the reading goals
+#[tauri::command]
+pub fn get_reading_goals(app_handle: AppHandle) -> CmdResult<Vec<ReadingGoal>> {
+    Ok(app_handle
+        .state::<ManagedState>()
+        .lock()?
+        .settings
+        .reading_goals
+        .clone())
+}
+
+/// Replaces the reading goals. Every goal needs a unique, non-empty id and a positive target,
+/// and a category when it's scoped to one. Goals that were removed or changed may be
+/// announced again once they are reached.
+#[tauri::command]
+pub fn set_reading_goals(app_handle: AppHandle, goals: Vec<ReadingGoal>) -> CmdResult<()> {
+    let mut ids = HashSet::new();
+    for goal in &goals {
+        if goal.id.trim().is_empty() {
+            return Err(anyhow::anyhow!("Reading goal id can't be empty").into());
+        }
+        if goal.target == 0 {
+            return Err(
+                anyhow::anyhow!("Target of reading goal {} must be positive", goal.id).into(),
+            );
+        }
+        if goal.category.as_ref().is_some_and(|c| c.trim().is_empty()) {
+            return Err(
+                anyhow::anyhow!("Category of reading goal {} can't be empty", goal.id).into(),
+            );
+        }
+        if !ids.insert(goal.id.as_str()) {
+            return Err(anyhow::anyhow!("Duplicate reading goal id: {}", goal.id).into());
+        }
+    }
+
+    info!("Setting {} reading goals", goals.len());
+    let state = app_handle.state::<ManagedState>();
+    let mut lock = state.lock()?;
+    // Only goals kept as they were stay reached
+    let unchanged: HashSet<&str> = goals
+        .iter()
+        .filter(|goal| lock.settings.reading_goals.contains(goal))
+        .map(|goal| goal.id.as_str())
+        .collect();
+    lock.reached_goals.retain(|key| {
+        key.rsplit_once(':')
+            .is_some_and(|(id, _)| unchanged.contains(id))
+    });
+    lock.update_settings(&app_handle, |s| s.reading_goals = goals)
+        .context("Failed to update reading goals")?;
+    Ok(())
+}
+
+/// Gets the backup schedule and retention
+#[tauri::command]
+pub fn get_backup_settings(app_handle: AppHandle) -> CmdResult<BackupSettings> {
+    Ok(app_handle
+        .state::<ManagedState>()
+        .lock()?
+        .settings
+        .backup_settings
+        .clone())
+}
+
+/// Sets the backup schedule and retention, at least one backup of each kind is kept
+#[tauri::command]
+pub fn set_backup_settings(
+    app_handle: AppHandle,
+    backup_settings: BackupSettings,
+) -> CmdResult<()> {
+    if backup_settings.keep == 0 {
+        return Err(anyhow::anyhow!("At least one backup must be kept").into());
+    }
+
+    let state = app_handle.state::<ManagedState>();
+    let mut lock = state.lock()?;
+    lock.update_settings(&app_handle, |s| s.backup_settings = backup_settings)
+        .context("Failed to update backup settings")?;
+    Ok(())
+}
+
+/// Gets the store files found unreadable on startup that were not restored yet
+#[tauri::command]
+pub fn get_corrupt_stores(app_handle: AppHandle) -> CmdResult<Vec<CorruptStore>> {
+    Ok(app_handle
+        .state::<ManagedState>()
+        .lock()?
+        .corrupt_stores
+        .clone())
+}
+
+/// Gets the notice about a library left in `store.json`, `None` when there is none
+#[tauri::command]
+pub fn get_store_notice(app_handle: AppHandle) -> CmdResult<Option<String>> {
+    Ok(app_handle
+        .state::<ManagedState>()
+        .lock()?
+        .store_notice
+        .clone())
+}
+
+/// Replaces a store file with its newest known-good copy and reloads what was read from it.
+/// Returns a notice to show when part of the restored file couldn't be applied.
+#[tauri::command]
+pub fn restore_store(app_handle: AppHandle, file: String) -> CmdResult<Option<String>> {
+    StoreRecovery::new(&app_handle)
+        .restore(&file)
+        .context("Failed to restore store file")?;
+    let notice = reload_store_file(&app_handle, &file)?;
+
+    app_handle
+        .state::<ManagedState>()
+        .lock()?
+        .corrupt_stores
+        .retain(|store| store.file != file);
+    Ok(notice)
+}
+
+/// Brings the app state in line with a store file that was replaced on disk,
+/// returning a notice for the user when part of it couldn't be applied
+pub(super) fn reload_store_file(app_handle: &AppHandle, file: &str) -> CmdResult<Option<String>> {
+    match file {
+        SETTINGS_FILE => {
+            let settings = SettingsStore::new(app_handle)?
+                .load()
+                .context("Failed to load restored settings")?;
+            app_handle.state::<ManagedState>().lock()?.settings = settings;
+        }
+        STORE_FILE => {
+            let notice =
+                setup::store::migrate(app_handle).context("Failed to migrate restored store")?;
+            // The migration may have imported games the repository doesn't hold yet
+            reload_games(app_handle)?;
+            app_handle.state::<ManagedState>().lock()?.store_notice = notice.clone();
+            return Ok(notice);
+        }
+        _ => {}
+    }
+    Ok(None)
+}
+
+/// Reloads the games repository from the database after it was changed on disk
+pub(super) fn reload_games(app_handle: &AppHandle) -> CmdResult<()> {
+    app_handle
+        .state::<GamesRepository>()
+        .reload(&app_handle.state::<Database>())
+        .context("Failed to reload games")?;
+    Ok(())
+}
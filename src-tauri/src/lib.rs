@@ -1,5 +1,11 @@
 use log::{error, info};
-use services::playtime;
+use services::{
+    backup::Backups,
+    game_watcher::GameWatcher,
+    playtime,
+    stores::{database::Database, file::StoreFile, games::GamesRepository},
+    system::ProcessIndex,
+};
 use tauri::{AppHandle, Manager, RunEvent};
 use tokio_util::sync::CancellationToken;
 
@@ -15,7 +21,11 @@ struct ShutdownToken(CancellationToken);
 fn setup_app(app: &AppHandle) -> anyhow::Result<()> {
     let token = app.state::<ShutdownToken>().0.clone();
     setup::run(app)?;
-    playtime::ExStaticPlaytime::spawn(app, token);
+    ProcessIndex::spawn(token.clone());
+    GamesRepository::spawn(app, token.clone());
+    Backups::spawn(app, token.clone());
+    playtime::ExStaticPlaytime::spawn(app, token.clone());
+    GameWatcher::spawn(app, token);
     Ok(())
 }
 
@@ -62,9 +72,12 @@ pub fn run() {
             commands::storage::delete_game,
             commands::storage::toggle_pin,
             commands::storage::reset_stats,
+            commands::storage::adjust_playtime,
+            commands::storage::get_audit_log,
             commands::storage::update_exe,
             commands::storage::set_game_categories,
             commands::storage::update_process,
+            commands::storage::update_process_matcher,
             commands::storage::get_categories,
             commands::storage::set_categories,
             commands::storage::get_selected_categories,
@@ -89,9 +102,46 @@ pub fn run() {
             commands::storage::set_hide_nsfw_images,
             commands::storage::get_jiten_base_url,
             commands::storage::set_jiten_base_url,
+            commands::storage::get_day_boundary,
+            commands::storage::set_day_boundary,
+            commands::storage::get_auto_detect_games,
+            commands::storage::set_auto_detect_games,
+            commands::storage::get_concurrent_playtime_policy,
+            commands::storage::set_concurrent_playtime_policy,
+            commands::storage::get_process_discovery_timeout,
+            commands::storage::set_process_discovery_timeout,
+            commands::storage::get_idle_timeout,
+            commands::storage::set_idle_timeout,
+            commands::storage::get_reading_goals,
+            commands::storage::set_reading_goals,
             commands::opener::open_game,
             commands::opener::close_game,
-            commands::opener::get_active_windows
+            commands::opener::get_active_windows,
+            commands::opener::test_process_matcher,
+            commands::opener::pause_tracking,
+            commands::opener::resume_tracking,
+            commands::sessions::get_sessions,
+            commands::sessions::get_sessions_in_range,
+            commands::sessions::create_manual_session,
+            commands::sessions::update_manual_session,
+            commands::sessions::delete_session,
+            commands::stats::get_daily_totals,
+            commands::stats::get_reading_speed,
+            commands::stats::get_stats,
+            commands::stats::get_goal_progress,
+            commands::stats::export_report,
+            commands::export::export_games,
+            commands::export::export_sessions,
+            commands::storage::get_corrupt_stores,
+            commands::storage::get_store_notice,
+            commands::storage::restore_store,
+            commands::storage::get_backup_settings,
+            commands::storage::set_backup_settings,
+            commands::backup::list_backups,
+            commands::backup::restore_backup,
+            commands::library::export_library,
+            commands::library::preview_library_import,
+            commands::library::import_library
         ])
         .build(tauri::generate_context!())
         .expect("error while building tauri application");
@@ -103,6 +153,18 @@ pub fn run() {
             token.cancel();
             // Give tasks a moment to shut down before the app forcefully closes.
             std::thread::sleep(std::time::Duration::from_millis(500));
+            // Trackers write their final playtime while shutting down, make sure it's on disk
+            let flushed = match (
+                app_handle.try_state::<GamesRepository>(),
+                app_handle.try_state::<Database>(),
+            ) {
+                (Some(repository), Some(database)) => repository.flush(&database),
+                _ => Ok(()),
+            };
+            if let Err(e) = flushed {
+                error!("Failed to flush games on exit: {:?}", e);
+            }
+            StoreFile::close_all(app_handle);
         }
     });
 }
@@ -1,11 +1,8 @@
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use tauri::Wry;
 
 pub type Result<T> = anyhow::Result<T>;
-pub type Store = Arc<tauri_plugin_store::Store<Wry>>;
 
-#[derive(Serialize, Debug, PartialEq, Default)]
+#[derive(Serialize, Debug, PartialEq, Default, Clone)]
 #[serde(tag = "type", content = "value", rename_all = "camelCase")]
 pub enum Fetchable<T> {
     /// The value has not been fetched yet.
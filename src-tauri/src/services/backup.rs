@@ -0,0 +1,377 @@
+use crate::{
+    prelude::Result,
+    services::{
+        state::ManagedState,
+        stores::{
+            database::Database,
+            file::{self, STORE_FILES, StoreFile},
+            games::GamesRepository,
+            settings::{BackupSettings, SettingsStore},
+        },
+    },
+    util,
+};
+use anyhow::Context;
+use chrono::Local;
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tauri::{AppHandle, Manager};
+use tokio_util::sync::CancellationToken;
+use zip::{CompressionMethod, ZipArchive, ZipWriter, write::SimpleFileOptions};
+
+/// Directory in the app data directory holding the backup archives
+const BACKUP_DIR: &str = "backups";
+/// Archive entry describing the backup
+const MANIFEST_ENTRY: &str = "backup.json";
+/// Archive entry holding a copy of the database
+const DATABASE_ENTRY: &str = "tadoku.db";
+/// Archive folder holding the cover images
+const IMAGES_ENTRY: &str = "images/";
+/// How often the scheduler checks whether a scheduled backup is due
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Why a backup was taken, retention applies to each reason separately
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupReason {
+    /// Taken on the interval of the backup settings
+    Scheduled,
+    /// Taken before `store.json` is migrated to a newer schema
+    Migration,
+    /// Taken before another backup is restored over the current data
+    Restore,
+    /// Taken before a library archive is imported
+    Import,
+}
+
+impl BackupReason {
+    fn key(self) -> &'static str {
+        match self {
+            Self::Scheduled => "scheduled",
+            Self::Migration => "migration",
+            Self::Restore => "restore",
+            Self::Import => "import",
+        }
+    }
+}
+
+/// Contents of the archive's `backup.json`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackupManifest {
+    /// Unix timestamp (seconds)
+    pub created_at: u64,
+    pub reason: BackupReason,
+    pub app_version: String,
+    pub includes_images: bool,
+}
+
+/// A backup archive in the backups directory
+#[derive(Serialize, Debug, Clone)]
+pub struct BackupInfo {
+    /// File name of the archive, passed back to restore it
+    pub name: String,
+    pub size_bytes: u64,
+    #[serde(flatten)]
+    pub manifest: BackupManifest,
+}
+
+/// Takes, lists, prunes and restores versioned backups of the store files,
+/// the database and optionally the cover images
+pub struct Backups<'a> {
+    app_handle: &'a AppHandle,
+}
+
+impl<'a> Backups<'a> {
+    pub fn new(app_handle: &'a AppHandle) -> Self {
+        Self { app_handle }
+    }
+
+    fn dir(&self) -> Result<PathBuf> {
+        Ok(self.app_handle.path().app_data_dir()?.join(BACKUP_DIR))
+    }
+
+    fn images_dir(&self) -> Result<PathBuf> {
+        Ok(self.app_handle.path().app_local_data_dir()?.join("images"))
+    }
+
+    /// Backup settings, read from the settings store while the app state isn't set up yet
+    fn settings(&self) -> Result<BackupSettings> {
+        match self.app_handle.try_state::<ManagedState>() {
+            Some(state) => Ok(state.lock()?.settings.backup_settings.clone()),
+            None => Ok(SettingsStore::new(self.app_handle)?.load()?.backup_settings),
+        }
+    }
+
+    /// Writes a new backup archive and prunes old ones of the same reason
+    pub fn create(&self, reason: BackupReason) -> Result<BackupInfo> {
+        let settings = self.settings()?;
+        let dir = self.dir()?;
+        fs::create_dir_all(&dir).context("Failed to create backups directory")?;
+
+        let name = format!(
+            "tadoku-{}-{}.zip",
+            Local::now().format("%Y%m%d-%H%M%S"),
+            reason.key()
+        );
+        let manifest = BackupManifest {
+            created_at: util::unix_now(),
+            reason,
+            app_version: self.app_handle.package_info().version.to_string(),
+            includes_images: settings.include_images,
+        };
+
+        info!("Creating {:?} backup {}", reason, name);
+        let path = dir.join(&name);
+        let tmp_path = path.with_extension("zip.tmp");
+        self.write_archive(&tmp_path, &manifest)
+            .context(format!("Failed to write backup {}", name))?;
+        fs::rename(&tmp_path, &path).context(format!("Failed to finish backup {}", name))?;
+
+        if let Err(e) = self.prune(reason, settings.keep) {
+            warn!("Failed to delete old backups: {:?}", e);
+        }
+
+        Ok(BackupInfo {
+            name,
+            size_bytes: fs::metadata(&path)?.len(),
+            manifest,
+        })
+    }
+
+    fn write_archive(&self, path: &Path, manifest: &BackupManifest) -> Result<()> {
+        let mut zip = ZipWriter::new(File::create(path)?);
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        zip.start_file(MANIFEST_ENTRY, options)?;
+        zip.write_all(&serde_json::to_vec_pretty(manifest)?)?;
+
+        for file in STORE_FILES {
+            let store_path = tauri_plugin_store::resolve_store_path(self.app_handle, file)?;
+            if store_path.exists() {
+                zip.start_file(file, options)?;
+                io::copy(&mut File::open(&store_path)?, &mut zip)?;
+            }
+        }
+
+        if let Some(database) = self.app_handle.try_state::<Database>() {
+            let snapshot = path.with_extension("db.tmp");
+            if snapshot.exists() {
+                fs::remove_file(&snapshot)?;
+            }
+            database.snapshot(&snapshot)?;
+            zip.start_file(DATABASE_ENTRY, options)?;
+            let copied = io::copy(&mut File::open(&snapshot)?, &mut zip);
+            fs::remove_file(&snapshot)?;
+            copied?;
+        }
+
+        if manifest.includes_images {
+            for entry in fs::read_dir(self.images_dir()?)? {
+                let entry = entry?;
+                if !entry.file_type()?.is_file() {
+                    continue;
+                }
+                let name = format!("{}{}", IMAGES_ENTRY, entry.file_name().to_string_lossy());
+                // Covers are compressed already
+                zip.start_file(name, options.compression_method(CompressionMethod::Stored))?;
+                io::copy(&mut File::open(entry.path())?, &mut zip)?;
+            }
+        }
+
+        zip.finish()?;
+        Ok(())
+    }
+
+    /// Backups in the backups directory, newest first
+    pub fn list(&self) -> Result<Vec<BackupInfo>> {
+        let dir = self.dir()?;
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut backups = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "zip") {
+                continue;
+            }
+            match Self::read_info(&path) {
+                Ok(info) => backups.push(info),
+                Err(e) => warn!("Skipping unreadable backup {:?}: {:?}", path, e),
+            }
+        }
+
+        backups.sort_by(|a, b| {
+            b.manifest
+                .created_at
+                .cmp(&a.manifest.created_at)
+                .then_with(|| b.name.cmp(&a.name))
+        });
+        Ok(backups)
+    }
+
+    fn read_info(path: &Path) -> Result<BackupInfo> {
+        let mut archive = ZipArchive::new(File::open(path)?)?;
+        let manifest = serde_json::from_reader(archive.by_name(MANIFEST_ENTRY)?)?;
+
+        Ok(BackupInfo {
+            name: path
+                .file_name()
+                .context("Backup has no file name")?
+                .to_string_lossy()
+                .into_owned(),
+            size_bytes: fs::metadata(path)?.len(),
+            manifest,
+        })
+    }
+
+    /// Deletes the oldest backups of `reason` beyond the newest `keep`
+    fn prune(&self, reason: BackupReason, keep: usize) -> Result<()> {
+        let dir = self.dir()?;
+        for backup in self
+            .list()?
+            .into_iter()
+            .filter(|b| b.manifest.reason == reason)
+            .skip(keep.max(1))
+        {
+            debug!("Deleting old backup {}", backup.name);
+            fs::remove_file(dir.join(&backup.name))?;
+        }
+        Ok(())
+    }
+
+    /// Takes a scheduled backup when the newest one is older than the configured interval
+    fn run_scheduled(&self) -> Result<()> {
+        let interval_hours = self.settings()?.interval_hours;
+        if interval_hours == 0 {
+            return Ok(());
+        }
+
+        let last = self
+            .list()?
+            .into_iter()
+            .find(|b| b.manifest.reason == BackupReason::Scheduled)
+            .map(|b| b.manifest.created_at);
+        let due =
+            last.is_none_or(|at| util::unix_now().saturating_sub(at) >= interval_hours * 3600);
+
+        if due {
+            self.create(BackupReason::Scheduled)?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the current data with the contents of a backup, taking a backup of it first.
+    /// Returns the store files that were replaced, their open stores are reloaded already.
+    /// The games repository still has to be reloaded, after `store.json` is migrated.
+    pub fn restore(&self, name: &str) -> Result<Vec<&'static str>> {
+        anyhow::ensure!(
+            Path::new(name).file_name().is_some_and(|n| n == name) && name.ends_with(".zip"),
+            "Invalid backup name: {}",
+            name
+        );
+        anyhow::ensure!(
+            self.app_handle
+                .state::<ManagedState>()
+                .lock()?
+                .games
+                .is_empty(),
+            "Stop tracking running games before restoring a backup"
+        );
+        let path = self.dir()?.join(name);
+        let mut archive =
+            ZipArchive::new(File::open(&path).context(format!("Backup {} not found", name))?)
+                .context(format!("Backup {} is not a valid archive", name))?;
+        archive
+            .by_name(MANIFEST_ENTRY)
+            .context(format!("Backup {} has no manifest", name))?;
+
+        // Everything is checked before anything is replaced
+        let mut stores = Vec::new();
+        for file in STORE_FILES {
+            let Ok(mut entry) = archive.by_name(file) else {
+                continue;
+            };
+            let mut bytes = Vec::new();
+            entry.read_to_end(&mut bytes)?;
+            anyhow::ensure!(
+                file::is_valid(&bytes),
+                "{} in backup {} is unreadable",
+                file,
+                name
+            );
+            stores.push((file, bytes));
+        }
+
+        let database = self.app_handle.state::<Database>();
+        if let Err(e) = self.app_handle.state::<GamesRepository>().flush(&database) {
+            error!("Failed to flush games before restoring: {:?}", e);
+        }
+        self.create(BackupReason::Restore)
+            .context("Failed to back up current data before restoring")?;
+        info!("Restoring backup {}", name);
+
+        // The database goes first, a copy it rejects leaves the store files untouched
+        if let Ok(mut entry) = archive.by_name(DATABASE_ENTRY) {
+            let copy = path.with_extension("db.tmp");
+            io::copy(&mut entry, &mut File::create(&copy)?)?;
+            let result = database.restore_from(&copy);
+            fs::remove_file(&copy)?;
+            result.context("Failed to restore database")?;
+        }
+
+        let mut restored = Vec::new();
+        for (file, bytes) in stores {
+            StoreFile::replace(self.app_handle, file, &bytes)?;
+            restored.push(file);
+        }
+
+        let images_dir = self.images_dir()?;
+        fs::create_dir_all(&images_dir)?;
+        for index in 0..archive.len() {
+            let mut entry = archive.by_index(index)?;
+            let Some(image) = entry
+                .enclosed_name()
+                .and_then(|p| p.strip_prefix(IMAGES_ENTRY).ok().map(Path::to_path_buf))
+            else {
+                continue;
+            };
+            if entry.is_file() && !image.as_os_str().is_empty() {
+                io::copy(&mut entry, &mut File::create(images_dir.join(image))?)?;
+            }
+        }
+
+        Ok(restored)
+    }
+
+    /// Checks for a due scheduled backup every `SCHEDULE_CHECK_INTERVAL` until shutdown
+    pub fn spawn(app_handle: &AppHandle, token: CancellationToken) {
+        let app_handle = app_handle.clone();
+
+        tauri::async_runtime::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = token.cancelled() => break,
+                    _ = tokio::time::sleep(SCHEDULE_CHECK_INTERVAL) => {}
+                }
+
+                let app_handle = app_handle.clone();
+                let result = tauri::async_runtime::spawn_blocking(move || {
+                    Backups::new(&app_handle).run_scheduled()
+                })
+                .await;
+                match result {
+                    Ok(Err(e)) => error!("Scheduled backup failed: {:?}", e),
+                    Err(e) => error!("Scheduled backup task failed: {}", e),
+                    Ok(Ok(())) => {}
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,282 @@
+use crate::{
+    prelude::{Fetchable, Result},
+    services::stores::{
+        games::{Game, Games},
+        sessions::{PlaySession, Sessions, TrackingSource},
+    },
+    util,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Version of the JSON export schema, bumped whenever a field is renamed or removed
+const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// Comma separated with a header row, list fields are joined with `; `
+    Csv,
+    /// An `ExportDocument`
+    Json,
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy)]
+#[serde(default)]
+pub struct ExportOptions {
+    /// Leave out NSFW games and their sessions
+    pub exclude_nsfw: bool,
+    /// Leave out game and session notes
+    pub exclude_notes: bool,
+}
+
+/// Top-level object of a JSON export
+#[derive(Serialize, Debug)]
+pub struct ExportDocument<T> {
+    pub schema_version: u32,
+    /// RFC 3339 UTC timestamp
+    pub exported_at: String,
+    /// `GameRecord`s or `SessionRecord`s
+    pub records: Vec<T>,
+}
+
+/// A library game as exported. Timestamps are RFC 3339 in UTC.
+#[derive(Serialize, Debug)]
+pub struct GameRecord {
+    /// Tadoku's id of the game, the VNDB id for games added from VNDB
+    pub id: String,
+    pub title: String,
+    pub alt_title: Option<String>,
+    /// `None` for games added by hand
+    pub vndb_id: Option<String>,
+    pub categories: Vec<String>,
+    /// Play time in seconds
+    pub playtime_secs: u64,
+    pub chars_read: u64,
+    /// Total characters of the game according to Jiten
+    pub jiten_char_count: Option<u64>,
+    pub first_played: Option<String>,
+    pub last_played: Option<String>,
+    pub is_nsfw: bool,
+    /// `None` when there are no notes or notes are excluded
+    pub notes: Option<String>,
+}
+
+impl GameRecord {
+    fn new(id: &str, game: &Game, options: &ExportOptions) -> Self {
+        Self {
+            id: id.to_string(),
+            title: game.title.clone(),
+            alt_title: match &game.alt_title {
+                Fetchable::Available(alt) => Some(alt.clone()),
+                _ => None,
+            },
+            vndb_id: id.starts_with('v').then(|| id.to_string()),
+            categories: game.categories.clone(),
+            playtime_secs: game.playtime,
+            chars_read: game.chars_read,
+            jiten_char_count: match game.jiten_char_count {
+                Fetchable::Available(count) => Some(count),
+                _ => None,
+            },
+            first_played: game.first_played.map(timestamp),
+            last_played: game.last_played.map(timestamp),
+            is_nsfw: game.is_nsfw,
+            notes: Some(game.notes.clone()).filter(|n| !options.exclude_notes && !n.is_empty()),
+        }
+    }
+}
+
+/// A play session as exported. Timestamps are RFC 3339 in UTC.
+#[derive(Serialize, Debug)]
+pub struct SessionRecord {
+    pub id: String,
+    pub game_id: String,
+    pub game_title: String,
+    pub started_at: String,
+    /// `None` while the session is running
+    pub ended_at: Option<String>,
+    /// Seconds credited to the game, pauses and idle time excluded
+    pub active_secs: u64,
+    pub chars_read: u64,
+    pub chars_per_hour: Option<u64>,
+    pub source: TrackingSource,
+    /// `None` when there is no note or notes are excluded
+    pub note: Option<String>,
+}
+
+impl SessionRecord {
+    fn new(session: &PlaySession, game: &Game, options: &ExportOptions) -> Self {
+        Self {
+            id: session.id.clone(),
+            game_id: session.game_id.clone(),
+            game_title: game.title.clone(),
+            started_at: timestamp(session.started_at),
+            ended_at: session.ended_at.map(timestamp),
+            active_secs: session.active_secs,
+            chars_read: session.chars_read(),
+            chars_per_hour: session.chars_per_hour,
+            source: session.source,
+            note: session.note.clone().filter(|_| !options.exclude_notes),
+        }
+    }
+}
+
+/// A record that can be written as a CSV row
+pub trait CsvRecord {
+    const HEADER: &'static [&'static str];
+
+    fn fields(&self) -> Vec<String>;
+}
+
+impl CsvRecord for GameRecord {
+    const HEADER: &'static [&'static str] = &[
+        "id",
+        "title",
+        "alt_title",
+        "vndb_id",
+        "categories",
+        "playtime_secs",
+        "chars_read",
+        "jiten_char_count",
+        "first_played",
+        "last_played",
+        "is_nsfw",
+        "notes",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.id.clone(),
+            self.title.clone(),
+            self.alt_title.clone().unwrap_or_default(),
+            self.vndb_id.clone().unwrap_or_default(),
+            self.categories.join("; "),
+            self.playtime_secs.to_string(),
+            self.chars_read.to_string(),
+            optional(self.jiten_char_count),
+            self.first_played.clone().unwrap_or_default(),
+            self.last_played.clone().unwrap_or_default(),
+            self.is_nsfw.to_string(),
+            self.notes.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+impl CsvRecord for SessionRecord {
+    const HEADER: &'static [&'static str] = &[
+        "id",
+        "game_id",
+        "game_title",
+        "started_at",
+        "ended_at",
+        "active_secs",
+        "chars_read",
+        "chars_per_hour",
+        "source",
+        "note",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        let source = match self.source {
+            TrackingSource::Classic => "classic",
+            TrackingSource::ExStatic => "ex_static",
+            TrackingSource::Manual => "manual",
+        };
+
+        vec![
+            self.id.clone(),
+            self.game_id.clone(),
+            self.game_title.clone(),
+            self.started_at.clone(),
+            self.ended_at.clone().unwrap_or_default(),
+            self.active_secs.to_string(),
+            self.chars_read.to_string(),
+            optional(self.chars_per_hour),
+            source.to_string(),
+            self.note.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+/// Library games ordered by title
+pub fn game_records(games: &Games, options: &ExportOptions) -> Vec<GameRecord> {
+    let mut records: Vec<GameRecord> = games
+        .iter()
+        .filter(|(_, game)| !(options.exclude_nsfw && game.is_nsfw))
+        .map(|(id, game)| GameRecord::new(id, game, options))
+        .collect();
+
+    records.sort_by(|a, b| a.title.cmp(&b.title).then_with(|| a.id.cmp(&b.id)));
+    records
+}
+
+/// Sessions of library games ordered by start time
+pub fn session_records(
+    games: &Games,
+    sessions: &Sessions,
+    options: &ExportOptions,
+) -> Vec<SessionRecord> {
+    sessions
+        .iter()
+        .filter_map(|session| {
+            let game = games.get(&session.game_id)?;
+            if options.exclude_nsfw && game.is_nsfw {
+                return None;
+            }
+            Some(SessionRecord::new(session, game, options))
+        })
+        .collect()
+}
+
+/// Writes records in the chosen format
+pub fn render<T: Serialize + CsvRecord>(records: Vec<T>, format: ExportFormat) -> Result<String> {
+    match format {
+        ExportFormat::Csv => Ok(to_csv(&records)),
+        ExportFormat::Json => {
+            let document = ExportDocument {
+                schema_version: SCHEMA_VERSION,
+                exported_at: timestamp(util::unix_now()),
+                records,
+            };
+            Ok(serde_json::to_string_pretty(&document)?)
+        }
+    }
+}
+
+fn to_csv<T: CsvRecord>(records: &[T]) -> String {
+    let mut out = String::new();
+    push_row(&mut out, T::HEADER.iter().copied());
+    for record in records {
+        push_row(&mut out, record.fields().iter().map(String::as_str));
+    }
+    out
+}
+
+/// Appends a CSV row, quoting fields as described in RFC 4180
+fn push_row<'a>(out: &mut String, fields: impl Iterator<Item = &'a str>) {
+    for (i, field) in fields.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            out.push('"');
+            out.push_str(&field.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(field);
+        }
+    }
+    out.push_str("\r\n");
+}
+
+fn optional(value: Option<u64>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// Unix timestamp (seconds) as RFC 3339 in UTC
+fn timestamp(secs: u64) -> String {
+    DateTime::<Utc>::from_timestamp(secs as i64, 0)
+        .unwrap_or_default()
+        .to_rfc3339_opts(SecondsFormat::Secs, true)
+}
@@ -3,19 +3,26 @@ use crate::{
     services::{
         discord::DiscordGameDetails,
         playtime,
-        state::{GameState, ManagedState},
-        stores::games::{Game, GamesStore},
-        system::SystemService,
+        state::{AppState, GameState, ManagedState},
+        stores::{
+            games::{Game, GamesStore},
+            sessions::{SessionsStore, TrackingSource},
+        },
+        system::{ProcessIndex, SystemService},
     },
+    util,
 };
 use anyhow::Context;
-use log::{debug, error};
+use log::{debug, error, info, warn};
 use serde_json::json;
 use std::{path::PathBuf, time::Duration};
-use sysinfo::{Pid, System};
+use sysinfo::Pid;
 use tauri::{AppHandle, Emitter, Manager};
 use tauri_plugin_shell::ShellExt;
 
+/// Checks, 100ms apart, for killed processes to exit before `close` returns
+const KILL_WAIT_ATTEMPTS: u32 = 50;
+
 pub struct GameManager<'a> {
     app_handle: &'a AppHandle,
 }
@@ -31,29 +38,109 @@ impl<'a> GameManager<'a> {
             .get(&game_id)
             .context(format!("Game not found in store: {}", game_id))?;
 
-        self.launch_process(&game)?;
-        self.spawn_monitor(game_id, game);
+        let timeout_secs = self
+            .app_handle
+            .state::<ManagedState>()
+            .lock()?
+            .settings
+            .process_discovery_timeout_secs;
+
+        let launcher = self.launch_process(&game)?;
+        self.spawn_monitor(game_id, game, launcher, timeout_secs);
         Ok(())
     }
 
-    pub fn close(&self) -> Result<()> {
-        let managed = self.app_handle.state::<ManagedState>();
-        let state = managed.lock()?;
+    /// Kills a running game's process, or every running game's when `game_id` is `None`
+    pub fn close(&self, game_id: Option<&str>) -> Result<()> {
+        let pids: Vec<u32> = {
+            let managed = self.app_handle.state::<ManagedState>();
+            let state = managed.lock()?;
 
-        if let Some(ref game) = state.game {
-            let mut system = System::new_all();
-            system.refresh_all();
+            state
+                .games
+                .values()
+                .filter(|g| game_id.is_none_or(|id| g.id == id))
+                .map(|g| g.pid)
+                .collect()
+        };
 
-            if let Some(process) = system.process(Pid::from_u32(game.pid)) {
-                if process.kill() {
-                    process.wait();
+        if pids.is_empty() {
+            return Ok(());
+        }
+
+        let pids: Vec<Pid> = pids.into_iter().map(Pid::from_u32).collect();
+        let index = ProcessIndex::global();
+
+        index.with(|system| {
+            for pid in &pids {
+                if let Some(process) = system.process(*pid) {
+                    process.kill();
                 }
             }
+        });
+
+        // Wait for the processes to exit without holding the shared index
+        for _ in 0..KILL_WAIT_ATTEMPTS {
+            index.refresh_pids(&pids);
+            if !pids.iter().any(|pid| index.is_alive(*pid)) {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+
+        Ok(())
+    }
+
+    /// Pauses or resumes playtime tracking of a running game
+    pub fn set_paused(&self, game_id: &str, paused: bool) -> Result<()> {
+        let managed = self.app_handle.state::<ManagedState>();
+        let mut state = managed.lock()?;
+
+        let game = state
+            .games
+            .get_mut(game_id)
+            .context(format!("Game is not running: {}", game_id))?;
+
+        if game.paused == paused {
+            return Ok(());
+        }
+
+        info!(
+            "{} tracking for game {}",
+            if paused { "Pausing" } else { "Resuming" },
+            game_id
+        );
+        game.paused = paused;
+        if !paused {
+            game.mark_activity();
+        }
+
+        if let Some(session_id) = &game.session_id {
+            let sessions = SessionsStore::new(self.app_handle)?;
+            let now = util::unix_now();
+            if paused {
+                sessions.start_pause(session_id, now)?;
+            } else {
+                sessions.end_pause(session_id, now)?;
+            }
         }
+
+        if let Err(e) = self.app_handle.emit(
+            "playtime",
+            json!({
+                "id": game_id,
+                "status": if paused { "paused" } else { "playing" },
+                "time": game.current_playtime
+            }),
+        ) {
+            error!("Error happened while emitting playtime: {}", e);
+        }
+
         Ok(())
     }
 
-    fn launch_process(&self, game: &Game) -> Result<()> {
+    /// Spawns the game's executable and returns the PID of the spawned process
+    fn launch_process(&self, game: &Game) -> Result<Pid> {
         let mut exe_path = PathBuf::from(&game.exe_file_path);
         let mut args = String::new();
 
@@ -74,14 +161,14 @@ impl<'a> GameManager<'a> {
             command = command.arg(args);
         }
 
-        command.spawn().context("Failed to spawn game process")?;
-        Ok(())
+        let (_, child) = command.spawn().context("Failed to spawn game process")?;
+        Ok(Pid::from_u32(child.pid()))
     }
 
-    fn spawn_monitor(&self, game_id: String, game: Game) {
+    fn spawn_monitor(&self, game_id: String, game: Game, launcher: Pid, timeout_secs: u64) {
         let app_handle = self.app_handle.clone();
         tauri::async_runtime::spawn(async move {
-            let pid = match Self::find_pid(&game.process_file_path).await {
+            let pid = match Self::find_pid(&game, launcher, timeout_secs).await {
                 Some(pid) => pid,
                 None => {
                     error!("Timeout: couldn't find process for {}", game_id);
@@ -89,17 +176,28 @@ impl<'a> GameManager<'a> {
                 }
             };
 
-            Self::set_game_state(&app_handle, &game_id, &game, pid);
-            Self::start_tracking(&app_handle, &game_id);
+            GameManager::new(&app_handle).attach(&game_id, &game, pid);
         });
     }
 
-    async fn find_pid(process_path: &str) -> Option<Pid> {
-        for attempt in 1..=60u8 {
-            if let Some(pid) = SystemService::get_pid_from_process_path(process_path) {
+    /// Starts tracking an already running game process.
+    /// Does nothing if the game is being tracked already.
+    pub fn attach(&self, game_id: &str, game: &Game, pid: Pid) {
+        if Self::set_game_state(self.app_handle, game_id, game, pid) {
+            Self::start_tracking(self.app_handle, game_id, pid);
+        }
+    }
+
+    /// Polls for the game's process once per second for up to `timeout_secs`,
+    /// following the process tree of `launcher`
+    async fn find_pid(game: &Game, launcher: Pid, timeout_secs: u64) -> Option<Pid> {
+        let attempts = timeout_secs.max(1);
+        for attempt in 1..=attempts {
+            if let Some(pid) = SystemService::find_game_process(game, Some(launcher)) {
+                debug!("Found process {} for {}", pid, game.process_file_path);
                 return Some(pid);
             }
-            if attempt == 60 {
+            if attempt == attempts {
                 break;
             }
             tokio::time::sleep(Duration::from_secs(1)).await;
@@ -107,22 +205,38 @@ impl<'a> GameManager<'a> {
         None
     }
 
-    fn set_game_state(app_handle: &AppHandle, game_id: &str, game: &Game, pid: Pid) {
+    /// Returns `false` when the game is already being tracked
+    fn set_game_state(app_handle: &AppHandle, game_id: &str, game: &Game, pid: Pid) -> bool {
         let managed = app_handle.state::<ManagedState>();
         let mut state = match managed.lock() {
             Ok(s) => s,
             Err(e) => {
                 error!("Failed to lock state: {}", e);
-                return;
+                return false;
             }
         };
 
-        state.game = Some(GameState {
-            pid: pid.as_u32(),
-            id: game_id.to_string(),
-            ..Default::default()
-        });
+        if state.games.contains_key(game_id) {
+            warn!("Game {} is already being tracked, not attaching", game_id);
+            return false;
+        }
 
+        state.games.insert(
+            game_id.to_string(),
+            GameState {
+                pid: pid.as_u32(),
+                id: game_id.to_string(),
+                started_at: util::unix_now(),
+                ..Default::default()
+            },
+        );
+
+        Self::show_presence(&mut state, game_id, game);
+        true
+    }
+
+    /// Shows a game in Discord presence
+    pub fn show_presence(state: &mut AppState, game_id: &str, game: &Game) {
         let settings = state.settings.clone();
 
         if let Some(pres) = &mut state.presence {
@@ -139,7 +253,19 @@ impl<'a> GameManager<'a> {
         }
     }
 
-    fn start_tracking(app_handle: &AppHandle, game_id: &str) {
+    /// Emits the most recently started running game, or `null` when none is left
+    pub fn emit_current_game(app_handle: &AppHandle, state: &AppState) {
+        let payload = match state.latest_game() {
+            Some(game) => json!({"id": game.id, "status": "playing", "pid": game.pid}),
+            None => json!(null),
+        };
+
+        if let Err(e) = app_handle.emit("current_game", payload) {
+            error!("Error emitting current_game event: {}", e);
+        }
+    }
+
+    fn start_tracking(app_handle: &AppHandle, game_id: &str, pid: Pid) {
         let store = match GamesStore::new(app_handle) {
             Ok(s) => s,
             Err(e) => {
@@ -148,18 +274,50 @@ impl<'a> GameManager<'a> {
             }
         };
 
-        playtime::ClassicPlaytime::spawn(app_handle);
+        Self::start_session(app_handle, &store, game_id);
+        playtime::ClassicPlaytime::spawn(app_handle, game_id);
 
         if let Err(e) = store.set_first_played(game_id) {
             error!("Error setting first played for {}: {}", game_id, e);
         }
 
-        if let Err(e) = app_handle.emit("current_game", json!({"id": game_id, "status": "playing"}))
-        {
+        if let Err(e) = app_handle.emit(
+            "current_game",
+            json!({"id": game_id, "status": "playing", "pid": pid.as_u32()}),
+        ) {
             error!("Error emitting current_game event: {}", e);
         }
     }
 
+    /// Opens a `PlaySession` for the game and links it to its `GameState`
+    fn start_session(app_handle: &AppHandle, store: &GamesStore, game_id: &str) {
+        let managed = app_handle.state::<ManagedState>();
+        let mut state = match managed.lock() {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Failed to lock state: {}", e);
+                return;
+            }
+        };
+
+        let source = TrackingSource::from(state.settings.playtime_mode);
+        let chars_read = store.get(game_id).map(|g| g.chars_read).unwrap_or_default();
+
+        let session = match SessionsStore::new(app_handle)
+            .and_then(|sessions| sessions.start(game_id, source, chars_read))
+        {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Error starting session for {}: {}", game_id, e);
+                return;
+            }
+        };
+
+        if let Some(game_state) = state.games.get_mut(game_id) {
+            game_state.session_id = Some(session.id);
+        }
+    }
+
     fn handle_open_lnk(exe_path: &mut PathBuf, args: &mut String) -> anyhow::Result<()> {
         debug!("Handling .lnk file: {:?}", exe_path);
         let lnk = lnk::ShellLink::open(&exe_path)
@@ -0,0 +1,80 @@
+use crate::services::{
+    game_manager::GameManager, state::ManagedState, stores::games::GamesStore,
+    system::SystemService,
+};
+use log::{debug, error, info};
+use std::{collections::HashSet, time::Duration};
+use tauri::{AppHandle, Manager};
+use tokio_util::sync::CancellationToken;
+
+/// Time between scans for running library games
+const WATCH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Attaches tracking to library games that were launched outside Tadoku
+/// (Steam, desktop shortcuts, or a game still running after Tadoku restarts)
+pub struct GameWatcher;
+
+impl GameWatcher {
+    pub fn spawn(app_handle: &AppHandle, token: CancellationToken) {
+        info!("Spawning game watcher task");
+        let app_handle = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = token.cancelled() => {
+                        info!("Shutdown signal received, stopping game watcher.");
+                        break;
+                    }
+                    _ = tokio::time::sleep(WATCH_INTERVAL) => Self::scan(&app_handle),
+                }
+            }
+        });
+    }
+
+    fn scan(app_handle: &AppHandle) {
+        let tracked: HashSet<String> = {
+            let managed = app_handle.state::<ManagedState>();
+            let state = match managed.lock() {
+                Ok(s) => s,
+                Err(e) => {
+                    error!("Failed to acquire app state mutex lock: {}", e);
+                    return;
+                }
+            };
+
+            if !state.settings.auto_detect_games {
+                return;
+            }
+
+            state.games.keys().cloned().collect()
+        };
+
+        let store = match GamesStore::new(app_handle) {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Failed to access store for game watcher: {}", e);
+                return;
+            }
+        };
+        let mut games = match store.get_all() {
+            Ok(games) => games,
+            Err(e) => {
+                error!("Failed to get games for game watcher: {}", e);
+                return;
+            }
+        };
+
+        games.retain(|id, _| !tracked.contains(id));
+
+        let Some((game_id, pid)) = SystemService::find_running_game(&games) else {
+            debug!("Game watcher: no library game running");
+            return;
+        };
+
+        info!("Detected running game {} (PID {})", game_id, pid);
+        // `get_all` rewrites image URLs to local paths, presence needs the stored one
+        if let Some(game) = store.get(&game_id) {
+            GameManager::new(app_handle).attach(&game_id, &game, pid);
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{
+    prelude::Result,
+    services::{
+        state::ManagedState,
+        stores::{
+            games::{Games, GamesRepository},
+            settings::{GoalMetric, GoalPeriod, ReadingGoal},
+        },
+    },
+    util::{self, DayBoundary},
+};
+use chrono::{Datelike, NaiveDate, TimeDelta};
+use log::{debug, error, info};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// How far along a reading goal is in its current period
+#[derive(Serialize, Debug, Clone)]
+pub struct GoalProgress {
+    pub goal_id: String,
+    /// Seconds or characters, depending on the goal's metric
+    pub current: u64,
+    pub target: u64,
+    pub percent: f64,
+    pub reached: bool,
+    /// First reading day (`YYYY-MM-DD`) of the current period
+    pub period_start: String,
+}
+
+impl GoalProgress {
+    fn key(&self) -> String {
+        format!("{}:{}", self.goal_id, self.period_start)
+    }
+}
+
+/// Evaluates reading goals against the daily ledgers and notifies the UI about them
+pub struct GoalTracker;
+
+impl GoalTracker {
+    /// First reading day of the period containing `today`
+    fn period_start(period: GoalPeriod, today: NaiveDate) -> NaiveDate {
+        match period {
+            GoalPeriod::Daily => today,
+            GoalPeriod::Weekly => {
+                today - TimeDelta::days(today.weekday().num_days_from_monday().into())
+            }
+            GoalPeriod::Monthly => today.with_day(1).unwrap_or(today),
+        }
+    }
+
+    fn progress_of(goal: &ReadingGoal, games: &Games, today: NaiveDate) -> GoalProgress {
+        let start = Self::period_start(goal.period, today)
+            .format("%Y-%m-%d")
+            .to_string();
+        let end = today.format("%Y-%m-%d").to_string();
+
+        let current = games
+            .values()
+            .filter(|g| {
+                goal.category
+                    .as_ref()
+                    .is_none_or(|category| g.categories.contains(category))
+            })
+            .flat_map(|g| match goal.metric {
+                GoalMetric::Playtime => g.daily_playtime.range(start.clone()..=end.clone()),
+                GoalMetric::Chars => g.daily_chars.range(start.clone()..=end.clone()),
+            })
+            .map(|(_, value)| value)
+            .sum();
+
+        GoalProgress {
+            goal_id: goal.id.clone(),
+            current,
+            target: goal.target,
+            percent: current as f64 / goal.target.max(1) as f64 * 100.0,
+            reached: current >= goal.target,
+            period_start: start,
+        }
+    }
+
+    /// Progress of every configured goal in its current period
+    pub fn progress(app_handle: &AppHandle) -> Result<Vec<GoalProgress>> {
+        let (goals, day): (Vec<ReadingGoal>, DayBoundary) = {
+            let managed = app_handle.state::<ManagedState>();
+            let state = managed.lock()?;
+            (
+                state.settings.reading_goals.clone(),
+                state.settings.day_boundary(),
+            )
+        };
+
+        if goals.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let today = day.day_of(util::unix_now());
+
+        app_handle.state::<GamesRepository>().read(|games| {
+            goals
+                .iter()
+                .map(|goal| Self::progress_of(goal, games, today))
+                .collect()
+        })
+    }
+
+    /// Marks goals already met in their current period, so they aren't announced again
+    /// after a restart
+    pub fn seed(app_handle: &AppHandle) -> Result<()> {
+        let progress = Self::progress(app_handle)?;
+
+        let managed = app_handle.state::<ManagedState>();
+        let mut state = managed.lock()?;
+        state
+            .reached_goals
+            .extend(progress.iter().filter(|p| p.reached).map(GoalProgress::key));
+
+        Ok(())
+    }
+
+    /// Re-evaluates the goals after tracked data changed, emitting `goal_progress`
+    /// and `goal_reached` for goals met for the first time in their period.
+    /// Must not be called while holding the app state lock.
+    pub fn evaluate(app_handle: &AppHandle) {
+        let progress = match Self::progress(app_handle) {
+            Ok(p) => p,
+            Err(e) => {
+                error!("Failed to evaluate reading goals: {:?}", e);
+                return;
+            }
+        };
+
+        if progress.is_empty() {
+            return;
+        }
+
+        let newly_reached: Vec<&GoalProgress> = {
+            let managed = app_handle.state::<ManagedState>();
+            let mut state = match managed.lock() {
+                Ok(s) => s,
+                Err(e) => {
+                    error!("Failed to acquire app state mutex lock: {}", e);
+                    return;
+                }
+            };
+
+            progress
+                .iter()
+                .filter(|p| p.reached && state.reached_goals.insert(p.key()))
+                .collect()
+        };
+
+        debug!("Evaluated {} reading goals", progress.len());
+        if let Err(e) = app_handle.emit("goal_progress", &progress) {
+            error!("Error emitting goal_progress event: {}", e);
+        }
+
+        for goal in newly_reached {
+            info!("Reading goal {} reached", goal.goal_id);
+            if let Err(e) = app_handle.emit("goal_reached", goal) {
+                error!("Error emitting goal_reached event: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{prelude::Result, services::state::GameState, util};
+use anyhow::Context;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, path::PathBuf};
+use tauri::{AppHandle, Manager};
+
+const JOURNAL_FILE: &str = "active_session.json";
+
+/// Snapshot of a running game's tracking state
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JournalEntry {
+    pub game_id: String,
+    pub session_id: Option<String>,
+    /// Seconds tracked since the game was launched
+    pub current_playtime: u64,
+    /// Seconds of `current_playtime` already written to the games store
+    pub flushed_playtime: u64,
+    /// Unix timestamp (seconds) of when the snapshot was taken
+    pub updated_at: u64,
+}
+
+impl JournalEntry {
+    /// Seconds that were tracked but never written to the games store
+    pub fn unflushed_playtime(&self) -> u64 {
+        self.current_playtime.saturating_sub(self.flushed_playtime)
+    }
+}
+
+/// On-disk journal of the in-progress sessions, used to recover playtime
+/// when Tadoku exits without closing them
+pub struct SessionJournal {
+    path: PathBuf,
+}
+
+impl SessionJournal {
+    pub fn new(app_handle: &AppHandle) -> Result<Self> {
+        let path = app_handle
+            .path()
+            .app_local_data_dir()
+            .context("Failed to get app local data directory")?
+            .join(JOURNAL_FILE);
+
+        Ok(Self { path })
+    }
+
+    /// Overwrites the journal with the current state of every running game.
+    /// Callers hold the app state lock, which keeps concurrent trackers from interleaving writes.
+    pub fn write(&self, games: &HashMap<String, GameState>) -> Result<()> {
+        if games.is_empty() {
+            return self.clear();
+        }
+
+        debug!("Writing session journal for {} game(s)", games.len());
+        let updated_at = util::unix_now();
+        let entries: Vec<JournalEntry> = games
+            .values()
+            .map(|game| JournalEntry {
+                game_id: game.id.clone(),
+                session_id: game.session_id.clone(),
+                current_playtime: game.current_playtime,
+                flushed_playtime: game.flushed_playtime,
+                updated_at,
+            })
+            .collect();
+
+        // Write to a temporary file first so a crash mid-write never leaves a truncated journal
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_vec(&entries)?)
+            .context("Failed to write session journal")?;
+        fs::rename(&tmp_path, &self.path).context("Failed to replace session journal")?;
+
+        Ok(())
+    }
+
+    /// Reads the journal left behind by a previous run
+    pub fn read(&self) -> Result<Vec<JournalEntry>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read(&self.path).context("Failed to read session journal")?;
+        serde_json::from_slice(&content).context("Failed to parse session journal")
+    }
+
+    /// Removes the journal once every session has been closed
+    pub fn clear(&self) -> Result<()> {
+        if self.path.exists() {
+            info!("Clearing session journal");
+            fs::remove_file(&self.path).context("Failed to remove session journal")?;
+        }
+        Ok(())
+    }
+}
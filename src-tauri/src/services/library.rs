@@ -0,0 +1,517 @@
+use crate::{
+    prelude::Result,
+    services::{
+        backup::{BackupReason, Backups},
+        state::ManagedState,
+        stores::{
+            categories::{Categories, CategoriesStore},
+            database::Database,
+            file::{self, SETTINGS_FILE, StoreFile},
+            games::{Game, Games, GamesRepository, ProcessMatcher},
+            sessions::{Sessions, SessionsStore},
+        },
+    },
+    util,
+};
+use anyhow::Context;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+use tauri::{AppHandle, Manager};
+use zip::{CompressionMethod, ZipArchive, ZipWriter, write::SimpleFileOptions};
+
+/// Version of the archive layout, bumped whenever an entry changes incompatibly
+const ARCHIVE_VERSION: u32 = 1;
+const MANIFEST_ENTRY: &str = "library.json";
+const GAMES_ENTRY: &str = "games.json";
+const SESSIONS_ENTRY: &str = "sessions.json";
+const CATEGORIES_ENTRY: &str = "categories.json";
+const SETTINGS_ENTRY: &str = "settings.json";
+/// Archive folder holding the cover images
+const IMAGES_ENTRY: &str = "images/";
+
+/// Contents of the archive's `library.json`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LibraryManifest {
+    pub archive_version: u32,
+    /// Unix timestamp (seconds)
+    pub exported_at: u64,
+    pub app_version: String,
+    pub games: usize,
+    pub sessions: usize,
+    pub images: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CategoryLists {
+    all: Categories,
+    selected: Categories,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// Adds the games missing from the library with their sessions, games already in it are kept
+    Merge,
+    /// Replaces the library, sessions and categories with the archive's
+    Replace,
+}
+
+/// Replaces the start of paths, e.g. `D:\Games` with `E:\VN`
+#[derive(Deserialize, Debug, Clone)]
+pub struct PathRemap {
+    pub from: String,
+    pub to: String,
+}
+
+fn is_separator(c: char) -> bool {
+    c == '\\' || c == '/'
+}
+
+impl PathRemap {
+    /// `path` starting with `to` instead of `from`, `None` when it doesn't start with `from`.
+    /// Whole path components are matched, ignoring ASCII case and the kind of slash.
+    fn apply(&self, path: &str) -> Option<String> {
+        let from = self.from.trim_end_matches(is_separator);
+        if from.is_empty() {
+            return None;
+        }
+
+        let head = path.get(..from.len())?;
+        let matches = head
+            .chars()
+            .zip(from.chars())
+            .all(|(a, b)| a.eq_ignore_ascii_case(&b) || (is_separator(a) && is_separator(b)));
+        let rest = &path[from.len()..];
+        if !matches || !(rest.is_empty() || rest.starts_with(is_separator)) {
+            return None;
+        }
+
+        Some(format!(
+            "{}{}",
+            self.to.trim_end_matches(is_separator),
+            rest
+        ))
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ImportOptions {
+    pub mode: ImportMode,
+    /// Tried in order, the first matching remap applies
+    #[serde(default)]
+    pub path_remaps: Vec<PathRemap>,
+    /// Also replace the settings with the archive's
+    #[serde(default)]
+    pub import_settings: bool,
+}
+
+/// A game both in the library and in the archive
+#[derive(Serialize, Debug, Clone)]
+pub struct ImportConflict {
+    pub game_id: String,
+    pub library_title: String,
+    pub archive_title: String,
+    /// Play time in seconds
+    pub library_playtime: u64,
+    pub archive_playtime: u64,
+}
+
+/// What an import changes, shown before it runs
+#[derive(Serialize, Debug, Clone)]
+pub struct ImportPreview {
+    pub archive: LibraryManifest,
+    /// Games not in the library yet
+    pub new_games: usize,
+    /// Merging keeps the library's version of these games, replacing takes the archive's
+    pub conflicts: Vec<ImportConflict>,
+    /// Library games missing from the archive, deleted when replacing
+    pub removed_games: usize,
+    pub sessions: usize,
+    /// Executable, process and matcher paths changed by the remaps
+    pub remapped_paths: usize,
+    pub images: usize,
+    pub settings: bool,
+}
+
+/// An archive read into memory, except for the images
+struct LibraryData {
+    manifest: LibraryManifest,
+    games: Games,
+    sessions: Sessions,
+    categories: CategoryLists,
+    settings: Option<Vec<u8>>,
+    /// Index and file name of each cover image entry
+    images: Vec<(usize, String)>,
+}
+
+/// What an import writes
+struct ImportPlan {
+    games: Games,
+    sessions: Sessions,
+    categories: CategoryLists,
+    preview: ImportPreview,
+}
+
+/// Moves a whole library between installs as a single zip archive of games, sessions,
+/// categories, settings and cover images
+pub struct LibraryArchive<'a> {
+    app_handle: &'a AppHandle,
+}
+
+impl<'a> LibraryArchive<'a> {
+    pub fn new(app_handle: &'a AppHandle) -> Self {
+        Self { app_handle }
+    }
+
+    fn images_dir(&self) -> Result<PathBuf> {
+        Ok(self.app_handle.path().app_local_data_dir()?.join("images"))
+    }
+
+    /// Writes the library to an archive at `path`
+    pub fn export(&self, path: &Path) -> Result<LibraryManifest> {
+        let games = self.app_handle.state::<GamesRepository>().get_all()?;
+        let sessions = SessionsStore::new(self.app_handle)?.get_all()?;
+        let categories_store = CategoriesStore::new(self.app_handle)?;
+        let categories = CategoryLists {
+            all: categories_store.get_all()?,
+            selected: categories_store.get_selected()?,
+        };
+        let settings = self
+            .app_handle
+            .state::<ManagedState>()
+            .lock()?
+            .settings
+            .clone();
+
+        let images: Vec<PathBuf> = fs::read_dir(self.images_dir()?)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+            .map(|entry| entry.path())
+            .collect();
+
+        let manifest = LibraryManifest {
+            archive_version: ARCHIVE_VERSION,
+            exported_at: util::unix_now(),
+            app_version: self.app_handle.package_info().version.to_string(),
+            games: games.len(),
+            sessions: sessions.len(),
+            images: images.len(),
+        };
+
+        info!(
+            "Exporting {} games, {} sessions and {} images to {:?}",
+            manifest.games, manifest.sessions, manifest.images, path
+        );
+        let tmp_path = path.with_extension("tmp");
+        let mut zip = ZipWriter::new(File::create(&tmp_path)?);
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        let entries: [(&str, Vec<u8>); 5] = [
+            (MANIFEST_ENTRY, serde_json::to_vec_pretty(&manifest)?),
+            (GAMES_ENTRY, serde_json::to_vec(&games)?),
+            (SESSIONS_ENTRY, serde_json::to_vec(&sessions)?),
+            (CATEGORIES_ENTRY, serde_json::to_vec_pretty(&categories)?),
+            (SETTINGS_ENTRY, serde_json::to_vec_pretty(&settings)?),
+        ];
+        for (name, bytes) in entries {
+            zip.start_file(name, options)?;
+            zip.write_all(&bytes)?;
+        }
+
+        for image in &images {
+            let name = image
+                .file_name()
+                .context("Image has no file name")?
+                .to_string_lossy();
+            // Covers are compressed already
+            zip.start_file(
+                format!("{}{}", IMAGES_ENTRY, name),
+                options.compression_method(CompressionMethod::Stored),
+            )?;
+            io::copy(&mut File::open(image)?, &mut zip)?;
+        }
+
+        zip.finish()?;
+        fs::rename(&tmp_path, path).context(format!("Failed to write {:?}", path))?;
+        Ok(manifest)
+    }
+
+    fn open(path: &Path) -> Result<(ZipArchive<File>, LibraryData)> {
+        let mut archive =
+            ZipArchive::new(File::open(path).context(format!("Failed to open {:?}", path))?)
+                .context("Not a library archive")?;
+
+        let manifest: LibraryManifest = serde_json::from_reader(
+            archive
+                .by_name(MANIFEST_ENTRY)
+                .context("Not a library archive")?,
+        )?;
+        anyhow::ensure!(
+            manifest.archive_version <= ARCHIVE_VERSION,
+            "The archive was exported by a newer version of Tadoku ({})",
+            manifest.app_version
+        );
+
+        let games = serde_json::from_reader(archive.by_name(GAMES_ENTRY)?)
+            .context("Failed to read games from archive")?;
+        let sessions = serde_json::from_reader(archive.by_name(SESSIONS_ENTRY)?)
+            .context("Failed to read sessions from archive")?;
+        let categories = serde_json::from_reader(archive.by_name(CATEGORIES_ENTRY)?)
+            .context("Failed to read categories from archive")?;
+        let settings = match archive.by_name(SETTINGS_ENTRY) {
+            Ok(mut entry) => {
+                let mut bytes = Vec::new();
+                entry.read_to_end(&mut bytes)?;
+                Some(bytes).filter(|bytes| file::is_valid(bytes))
+            }
+            Err(_) => None,
+        };
+
+        // Covers are exported flat, so a nested entry could not be referenced by file name
+        let mut images = Vec::new();
+        for index in 0..archive.len() {
+            let entry = archive.by_index(index)?;
+            let Some(image) = entry
+                .enclosed_name()
+                .and_then(|p| p.strip_prefix(IMAGES_ENTRY).ok().map(Path::to_path_buf))
+                .filter(|p| !p.as_os_str().is_empty())
+            else {
+                continue;
+            };
+            anyhow::ensure!(
+                entry.is_file() && image.components().count() == 1,
+                "Unexpected nested image in archive: {}",
+                entry.name()
+            );
+            images.push((index, image.to_string_lossy().into_owned()));
+        }
+
+        Ok((
+            archive,
+            LibraryData {
+                manifest,
+                games,
+                sessions,
+                categories,
+                settings,
+                images,
+            },
+        ))
+    }
+
+    /// Works out what importing `data` does to the current library
+    fn plan(&self, data: LibraryData, options: &ImportOptions) -> Result<ImportPlan> {
+        let library = self.app_handle.state::<GamesRepository>().get_all()?;
+        let library_sessions: HashSet<String> = SessionsStore::new(self.app_handle)?
+            .get_all()?
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        let categories_store = CategoriesStore::new(self.app_handle)?;
+        let images_dir = self.images_dir()?;
+        let images: HashSet<&str> = data.images.iter().map(|(_, name)| name.as_str()).collect();
+
+        let mut preview = ImportPreview {
+            removed_games: match options.mode {
+                ImportMode::Merge => 0,
+                ImportMode::Replace => library
+                    .keys()
+                    .filter(|id| !data.games.contains_key(*id))
+                    .count(),
+            },
+            images: data.manifest.images,
+            archive: data.manifest,
+            new_games: 0,
+            conflicts: Vec::new(),
+            sessions: 0,
+            remapped_paths: 0,
+            settings: options.import_settings && data.settings.is_some(),
+        };
+
+        let mut games = Games::new();
+        for (game_id, mut game) in data.games {
+            match library.get(&game_id) {
+                Some(existing) => {
+                    preview.conflicts.push(ImportConflict {
+                        game_id: game_id.clone(),
+                        library_title: existing.title.clone(),
+                        archive_title: game.title.clone(),
+                        library_playtime: existing.playtime,
+                        archive_playtime: game.playtime,
+                    });
+                    if options.mode == ImportMode::Merge {
+                        continue;
+                    }
+                }
+                None => preview.new_games += 1,
+            }
+            preview.remapped_paths += remap_game(&mut game, &options.path_remaps);
+            rebase_images(&mut game, &images_dir, &images);
+            games.insert(game_id, game);
+        }
+        preview
+            .conflicts
+            .sort_by(|a, b| a.library_title.cmp(&b.library_title));
+
+        let (sessions, categories): (Sessions, CategoryLists) = match options.mode {
+            ImportMode::Merge => {
+                let sessions = data
+                    .sessions
+                    .into_iter()
+                    .filter(|s| games.contains_key(&s.game_id) && !library_sessions.contains(&s.id))
+                    .collect();
+
+                let mut all = categories_store.get_all()?;
+                for category in data.categories.all {
+                    if !all.contains(&category) {
+                        all.push(category);
+                    }
+                }
+                let selected = categories_store.get_selected()?;
+                (sessions, CategoryLists { all, selected })
+            }
+            ImportMode::Replace => {
+                let sessions = data
+                    .sessions
+                    .into_iter()
+                    .filter(|s| games.contains_key(&s.game_id))
+                    .collect();
+                (sessions, data.categories)
+            }
+        };
+        preview.sessions = sessions.len();
+
+        Ok(ImportPlan {
+            games,
+            sessions,
+            categories,
+            preview,
+        })
+    }
+
+    /// Shows what importing the archive at `path` would change, without changing anything
+    pub fn preview(&self, path: &Path, options: &ImportOptions) -> Result<ImportPreview> {
+        let (_, data) = Self::open(path)?;
+        Ok(self.plan(data, options)?.preview)
+    }
+
+    /// Imports the archive at `path`, taking a backup of the current data first.
+    /// When the preview's `settings` is set, `settings.json` was replaced and still has to be
+    /// reloaded into the app state.
+    pub fn import(&self, path: &Path, options: &ImportOptions) -> Result<ImportPreview> {
+        anyhow::ensure!(
+            self.app_handle
+                .state::<ManagedState>()
+                .lock()?
+                .games
+                .is_empty(),
+            "Stop tracking running games before importing a library"
+        );
+
+        let (mut archive, data) = Self::open(path)?;
+        let settings = data.settings.clone().filter(|_| options.import_settings);
+        let images = data.images.clone();
+        let plan = self.plan(data, options)?;
+
+        Backups::new(self.app_handle)
+            .create(BackupReason::Import)
+            .context("Failed to back up current data before importing")?;
+        info!(
+            "Importing library from {:?}: {} games, {} sessions",
+            path,
+            plan.games.len(),
+            plan.sessions.len()
+        );
+
+        // Copied first, so the imported games never point at a missing cover
+        let images_dir = self.images_dir()?;
+        fs::create_dir_all(&images_dir)?;
+        for (index, image) in images {
+            let target = images_dir.join(image);
+            if options.mode == ImportMode::Merge && target.exists() {
+                continue;
+            }
+            io::copy(&mut archive.by_index(index)?, &mut File::create(&target)?)?;
+        }
+
+        let database = self.app_handle.state::<Database>();
+        let repository = self.app_handle.state::<GamesRepository>();
+        if let Err(e) = repository.flush(&database) {
+            error!("Failed to flush games before importing: {:?}", e);
+        }
+        database
+            .write_library(
+                &plan.games,
+                &plan.sessions,
+                &plan.categories.all,
+                &plan.categories.selected,
+                options.mode == ImportMode::Replace,
+            )
+            .context("Failed to write imported library")?;
+        repository.reload(&database)?;
+
+        if let Some(settings) = settings {
+            StoreFile::replace(self.app_handle, SETTINGS_FILE, &settings)?;
+        }
+
+        Ok(plan.preview)
+    }
+}
+
+/// Applies the first matching remap to a path, returning whether it changed
+fn remap(path: &mut String, remaps: &[PathRemap]) -> bool {
+    match remaps.iter().find_map(|remap| remap.apply(path)) {
+        Some(remapped) => {
+            *path = remapped;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Remaps the paths a game is launched and recognized by, returning how many changed
+fn remap_game(game: &mut Game, remaps: &[PathRemap]) -> usize {
+    let mut changed = 0;
+    for path in [&mut game.exe_file_path, &mut game.process_file_path] {
+        changed += remap(path, remaps) as usize;
+    }
+    if let ProcessMatcher::PathGlob(path) | ProcessMatcher::InstallDir(Some(path)) =
+        &mut game.process_matcher
+    {
+        changed += remap(path, remaps) as usize;
+    }
+    changed
+}
+
+/// Points a cover path from another install at the same file in `images_dir`,
+/// when the archive holds a cover by that name. URLs and bare file names are kept.
+fn rebase_image(path: &mut String, images_dir: &Path, images: &HashSet<&str>) {
+    if path.contains("://") {
+        return;
+    }
+    let Some((_, name)) = path.rsplit_once(is_separator) else {
+        return;
+    };
+    if images.contains(name) {
+        *path = images_dir.join(name).to_string_lossy().into_owned();
+    }
+}
+
+/// Rebases the cover, icon and character image paths of a game onto `images_dir`
+fn rebase_images(game: &mut Game, images_dir: &Path, images: &HashSet<&str>) {
+    rebase_image(&mut game.image_url, images_dir, images);
+    if let Some(icon) = &mut game.icon_url {
+        rebase_image(icon, images_dir, images);
+    }
+    for character in game.characters.iter_mut().flatten() {
+        if let Some(image) = &mut character.image_url {
+            rebase_image(image, images_dir, images);
+        }
+    }
+}
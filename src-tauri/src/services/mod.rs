@@ -1,9 +1,18 @@
+pub mod backup;
 pub mod discord;
+pub mod export;
 pub mod game_manager;
 pub mod game_saver;
+pub mod game_watcher;
+pub mod goals;
 pub mod jiten;
+pub mod journal;
+pub mod library;
 pub mod playtime;
+pub mod report;
+pub mod session_manager;
 pub mod state;
+pub mod stats;
 pub mod stores;
 pub mod system;
 pub mod vndb;
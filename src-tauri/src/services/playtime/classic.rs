@@ -1,178 +1,532 @@
-use super::super::stores::games::GamesStore;
-use crate::services::{state::ManagedState, stores::settings::PlaytimeMode};
+use super::super::stores::{
+    games::{Game, GamesStore},
+    sessions::SessionsStore,
+};
+use crate::{
+    ShutdownToken,
+    prelude::Result,
+    services::{
+        game_manager::GameManager,
+        goals::GoalTracker,
+        journal::SessionJournal,
+        state::{GameState, ManagedState},
+        stores::settings::{ConcurrentPlaytimePolicy, PlaytimeMode},
+        system::{ProcessIndex, SystemService},
+    },
+    util::{self, DayBoundary},
+};
+use anyhow::Context;
 use log::{debug, error, info, warn};
-use std::{thread, time::Duration};
-use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, RefreshKind, System};
+use std::time::{Duration, Instant, SystemTime};
+use sysinfo::Pid;
 use tauri::{AppHandle, Emitter, Manager};
+use tokio::time::MissedTickBehavior;
+
+/// Time between checks of a tracked game
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+/// Seconds between writes of tracked playtime to the games store
+const FLUSH_INTERVAL_SECS: u64 = 60;
+/// Time between snapshots of the running games in the session journal
+const JOURNAL_INTERVAL: Duration = Duration::from_secs(5);
+/// Time to wait for a replacement process after the tracked one exits,
+/// covers games that restart themselves
+const REATTACH_GRACE: Duration = Duration::from_secs(5);
+/// A gap between ticks longer than this means the machine was suspended
+/// (or the clock jumped), the gap is not credited
+const SUSPEND_GAP: Duration = Duration::from_secs(30);
+
+/// Settings and tracking state read at the start of a tick
+struct Snapshot {
+    game_state: GameState,
+    playtime_mode: PlaytimeMode,
+    policy: ConcurrentPlaytimePolicy,
+    day: DayBoundary,
+    idle_timeout_secs: u64,
+}
+
+/// Measures real elapsed time between ticks with both a monotonic and the wall clock
+struct Clock {
+    last_instant: Instant,
+    last_wall: SystemTime,
+}
+
+impl Clock {
+    fn new() -> Self {
+        Self {
+            last_instant: Instant::now(),
+            last_wall: SystemTime::now(),
+        }
+    }
+
+    /// Time elapsed since the previous call, `None` when the gap looks like a suspend.
+    /// Depending on the platform only one of the clocks advances during sleep, so both are checked.
+    fn lap(&mut self) -> Option<Duration> {
+        let now = Instant::now();
+        let wall = SystemTime::now();
+        let monotonic = now.duration_since(self.last_instant);
+        // A clock set backwards yields an error, which counts as no wall time passing
+        let wall_elapsed = wall.duration_since(self.last_wall).unwrap_or_default();
+        self.last_instant = now;
+        self.last_wall = wall;
+
+        let gap = monotonic.max(wall_elapsed);
+        if gap > SUSPEND_GAP {
+            info!(
+                "Detected a {}s gap between ticks, not crediting it",
+                gap.as_secs()
+            );
+            return None;
+        }
+
+        Some(monotonic)
+    }
+}
+
+/// State of a single game's tracking task
+struct Tracker {
+    app_handle: AppHandle,
+    game_id: String,
+    game: Game,
+    store: GamesStore,
+    journal: SessionJournal,
+    /// Sub-second remainder of credited time, carried over to the next tick
+    carry: Duration,
+    last_window: Option<(u32, String)>,
+    last_journal: Instant,
+    missing_since: Option<Instant>,
+    /// Playtime was written to the store since reading goals were last evaluated
+    goals_dirty: bool,
+}
 
 pub struct ClassicPlaytime;
 
 impl ClassicPlaytime {
-    pub fn spawn(app_handle: &AppHandle) {
-        info!("Spawning classic playtime tracking task");
+    /// Whether a game may accrue playtime under the concurrent playtime policy
+    fn is_focused(pid: u32, policy: ConcurrentPlaytimePolicy) -> bool {
+        if policy == ConcurrentPlaytimePolicy::AllRunning {
+            return true;
+        }
+
+        #[cfg(windows)]
+        {
+            match x_win::get_active_window() {
+                Ok(active_window) => active_window.id == pid,
+                Err(_) => {
+                    debug!("Failed to get active window");
+                    false
+                }
+            }
+        }
+
+        #[cfg(not(windows))]
+        {
+            // Focus can't be detected here, so every running game counts
+            let _ = pid;
+            true
+        }
+    }
+
+    /// Identifies the focused window, a change of it counts as reader activity
+    fn active_window() -> Option<(u32, String)> {
+        #[cfg(windows)]
+        {
+            x_win::get_active_window()
+                .ok()
+                .map(|window| (window.id, window.title))
+        }
+
+        #[cfg(not(windows))]
+        {
+            None
+        }
+    }
+
+    /// Spawns a tracking task for a running game, it ends once the game's process exits
+    /// or Tadoku shuts down
+    pub fn spawn(app_handle: &AppHandle, game_id: &str) {
+        info!("Spawning classic playtime tracking task for {}", game_id);
+        let token = app_handle.state::<ShutdownToken>().0.clone();
         let app_handle = app_handle.clone();
+        let game_id = game_id.to_string();
         tauri::async_runtime::spawn(async move {
             debug!("Classic playtime task started");
-            let mut system = System::new_with_specifics(
-                RefreshKind::nothing().with_processes(ProcessRefreshKind::everything()),
-            );
-            let store = match GamesStore::new(&app_handle) {
-                Ok(s) => s,
+            let mut tracker = match Tracker::new(app_handle, game_id) {
+                Ok(t) => t,
                 Err(e) => {
-                    error!("Error happened while accessing store: {}", e);
+                    error!("Failed to start classic playtime tracking: {:?}", e);
                     return;
                 }
             };
 
+            let mut ticker = tokio::time::interval(TICK_INTERVAL);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            let mut clock = Clock::new();
+
             loop {
-                let (pid, game_id, current_playtime, playtime_mode) = {
-                    let state = app_handle.state::<ManagedState>();
-                    let mut state = match state.lock() {
-                        Ok(s) => s,
-                        Err(e) => {
-                            error!("Failed to acquire app state mutex lock: {}", e);
-                            return;
+                tokio::select! {
+                    _ = token.cancelled() => {
+                        info!("Shutdown signal received, stopping tracking of {}", tracker.game_id);
+                        if let Some(elapsed) = clock.lap() {
+                            tracker.credit_remainder(elapsed);
                         }
-                    };
-
-                    let game_state = match state.game.as_mut().ok_or("Couldn't find the game") {
-                        Ok(g) => g,
-                        Err(e) => {
-                            warn!("No active game found: {}", e);
-                            return;
+                        tracker.finish();
+                        break;
+                    }
+                    _ = ticker.tick() => {
+                        // Process lookups wait out index refreshes, keep them off the async workers
+                        let elapsed = clock.lap();
+                        let tick = tauri::async_runtime::spawn_blocking(move || {
+                            let running = tracker.tick(elapsed);
+                            (tracker, running)
+                        });
+                        match tick.await {
+                            Ok((t, true)) => tracker = t,
+                            Ok((_, false)) => break,
+                            Err(e) => {
+                                error!("Classic playtime tick failed: {}", e);
+                                break;
+                            }
                         }
-                    };
-
-                    debug!(
-                        "Current game details - PID: {}, Game ID: {}",
-                        game_state.pid, game_state.id
-                    );
-
-                    (
-                        game_state.pid,
-                        game_state.id.clone(),
-                        game_state.current_playtime,
-                        state.settings.playtime_mode,
-                    )
-                };
-
-                system.refresh_processes_specifics(
-                    ProcessesToUpdate::All,
-                    true,
-                    ProcessRefreshKind::everything(),
+                    }
+                }
+            }
+        });
+    }
+}
+
+impl Tracker {
+    fn new(app_handle: AppHandle, game_id: String) -> Result<Self> {
+        let store = GamesStore::new(&app_handle).context("Failed to access store")?;
+        let journal =
+            SessionJournal::new(&app_handle).context("Failed to access session journal")?;
+        let game = store
+            .get(&game_id)
+            .context(format!("Game {} not found in store", game_id))?;
+
+        Ok(Self {
+            app_handle,
+            game_id,
+            game,
+            store,
+            journal,
+            carry: Duration::ZERO,
+            last_window: None,
+            last_journal: Instant::now(),
+            missing_since: None,
+            goals_dirty: false,
+        })
+    }
+
+    fn snapshot(&self) -> Option<Snapshot> {
+        let state = self.app_handle.state::<ManagedState>();
+        let state = match state.lock() {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Failed to acquire app state mutex lock: {}", e);
+                return None;
+            }
+        };
+
+        let Some(game_state) = state.games.get(&self.game_id) else {
+            warn!("Game {} is no longer tracked", self.game_id);
+            return None;
+        };
+
+        debug!(
+            "Current game details - PID: {}, Game ID: {}",
+            game_state.pid, game_state.id
+        );
+
+        Some(Snapshot {
+            game_state: game_state.clone(),
+            playtime_mode: state.settings.playtime_mode,
+            policy: state.settings.concurrent_playtime_policy,
+            day: state.settings.day_boundary(),
+            idle_timeout_secs: state.settings.idle_timeout_mins.saturating_mul(60),
+        })
+    }
+
+    fn emit_playtime(&self, status: &str, time: u64) {
+        if let Err(e) = self.app_handle.emit(
+            "playtime",
+            serde_json::json!({
+                "id": self.game_id,
+                "status": status,
+                "time": time
+            }),
+        ) {
+            error!("Error happened while emitting playtime: {}", e);
+        }
+    }
+
+    /// Runs one tracking step, returns false once tracking has ended
+    fn tick(&mut self, elapsed: Option<Duration>) -> bool {
+        let Some(snapshot) = self.snapshot() else {
+            return false;
+        };
+        let pid = Pid::from_u32(snapshot.game_state.pid);
+
+        if !ProcessIndex::global().is_alive(pid) {
+            return self.handle_missing_process();
+        }
+        self.missing_since = None;
+
+        if !matches!(snapshot.playtime_mode, PlaytimeMode::Classic) {
+            debug!("Playtime mode is not Classic, skipping tick");
+            self.carry = Duration::ZERO;
+            return true;
+        }
+
+        if snapshot.game_state.paused {
+            debug!("Tracking is paused manually");
+            self.carry = Duration::ZERO;
+            self.emit_playtime("paused", snapshot.game_state.current_playtime);
+            return true;
+        }
+
+        if !ClassicPlaytime::is_focused(snapshot.game_state.pid, snapshot.policy) {
+            debug!("Game is not active, pausing playtime");
+            self.carry = Duration::ZERO;
+            self.emit_playtime("paused", snapshot.game_state.current_playtime);
+            return true;
+        }
+
+        let window = ClassicPlaytime::active_window();
+        let window_changed = window.is_some() && window != self.last_window;
+        self.last_window = window;
+
+        let Some(elapsed) = elapsed else {
+            self.carry = Duration::ZERO;
+            return true;
+        };
+
+        let Some((status, time)) = self.credit(&snapshot, elapsed, window_changed) else {
+            return false;
+        };
+        self.emit_playtime(status, time);
+
+        if self.goals_dirty {
+            self.goals_dirty = false;
+            GoalTracker::evaluate(&self.app_handle);
+        }
+
+        true
+    }
+
+    /// Credits elapsed time to the running game, returns the resulting status and playtime
+    fn credit(
+        &mut self,
+        snapshot: &Snapshot,
+        elapsed: Duration,
+        window_changed: bool,
+    ) -> Option<(&'static str, u64)> {
+        let state = self.app_handle.state::<ManagedState>();
+        let mut state = match state.lock() {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Failed to acquire app state mutex lock: {}", e);
+                return None;
+            }
+        };
+        let Some(tracked) = state.games.get_mut(&self.game_id) else {
+            warn!("Game {} is no longer tracked", self.game_id);
+            return None;
+        };
+
+        if window_changed || snapshot.idle_timeout_secs == 0 {
+            tracked.mark_activity();
+        }
+
+        if tracked.idle {
+            debug!("Game is idle, waiting for activity");
+            self.carry = Duration::ZERO;
+            return Some(("idle", tracked.current_playtime));
+        }
+
+        self.carry += elapsed;
+        let secs = self.carry.as_secs();
+        self.carry -= Duration::from_secs(secs);
+
+        debug!("Game is active, crediting {}s of playtime", secs);
+        tracked.current_playtime += secs;
+
+        if snapshot.idle_timeout_secs > 0 {
+            tracked.idle_secs += secs;
+
+            if tracked.idle_secs >= snapshot.idle_timeout_secs {
+                info!(
+                    "No activity in game {} for {}s, taking back idle time",
+                    self.game_id, tracked.idle_secs
                 );
+                tracked.current_playtime -= tracked.idle_secs;
+                tracked.idle_secs = 0;
+                tracked.idle = true;
+            }
+        }
 
-                let process_playtime = system
-                    .process(Pid::from(pid as usize))
-                    .map(|p| p.run_time());
+        // The idle tail stays unflushed until activity confirms it
+        let unflushed = tracked
+            .current_playtime
+            .saturating_sub(tracked.flushed_playtime + tracked.idle_secs);
+        if unflushed >= FLUSH_INTERVAL_SECS {
+            match self
+                .store
+                .update_playtime(&self.game_id, unflushed, &snapshot.day)
+            {
+                Ok(()) => {
+                    tracked.flushed_playtime += unflushed;
+                    self.goals_dirty = true;
+                }
+                Err(e) => error!("Error happened while updating playtime: {}", e),
+            }
+        }
 
-                match process_playtime {
-                    Some(_) => {
-                        debug!("Sleeping for 1 second");
-                        thread::sleep(Duration::from_secs(1));
+        let status = if tracked.idle { "idle" } else { "playing" };
+        let time = tracked.current_playtime;
 
-                        if !matches!(playtime_mode, PlaytimeMode::Classic) {
-                            debug!("Playtime mode is not Classic, skipping iteration");
-                            continue;
-                        }
+        if self.last_journal.elapsed() >= JOURNAL_INTERVAL {
+            self.last_journal = Instant::now();
+            if let Err(e) = self.journal.write(&state.games) {
+                error!("Error happened while writing session journal: {}", e);
+            }
+        }
 
-                        debug!("Playtime mode is Classic, proceeding with tracking");
-
-                        #[cfg(windows)]
-                        {
-                            if let Ok(active_window) = x_win::get_active_window() {
-                                if active_window.id != pid {
-                                    debug!("Game is not active, pausing playtime");
-
-                                    if let Err(e) = app_handle.emit(
-                                        "playtime",
-                                        serde_json::json!({
-                                            "status": "paused",
-                                            "time": current_playtime
-                                        }),
-                                    ) {
-                                        error!("Error happened while emitting playtime: {}", e);
-                                    }
-
-                                    continue;
-                                }
-                            } else {
-                                debug!("Failed to get active window");
-                                continue;
-                            }
-                        }
+        Some((status, time))
+    }
 
-                        debug!("Game is active, incrementing playtime");
-                        {
-                            let state = app_handle.state::<ManagedState>();
-                            let mut state = match state.lock() {
-                                Ok(s) => s,
-                                Err(e) => {
-                                    error!("Failed to acquire app state mutex lock: {}", e);
-                                    return;
-                                }
-                            };
-                            let game_state =
-                                match state.game.as_mut().ok_or("Couldn't find the game") {
-                                    Ok(g) => g,
-                                    Err(e) => {
-                                        warn!("No active game found: {}", e);
-                                        return;
-                                    }
-                                };
-                            game_state.current_playtime += 1;
-                        }
+    /// Credits the time elapsed since the last tick when tracking stops in between ticks
+    fn credit_remainder(&mut self, elapsed: Duration) {
+        let Some(snapshot) = self.snapshot() else {
+            return;
+        };
+        let game_state = &snapshot.game_state;
 
-                        if current_playtime != 0 && current_playtime % 60 == 0 {
-                            if let Err(e) = store.update_playtime(&game_id, 60) {
-                                error!("Error happened while updating playtime: {}", e);
-                            }
-                        }
+        let accruing = matches!(snapshot.playtime_mode, PlaytimeMode::Classic)
+            && !game_state.paused
+            && ClassicPlaytime::is_focused(game_state.pid, snapshot.policy);
 
-                        if let Err(e) = app_handle.emit(
-                            "playtime",
-                            serde_json::json!({
-                                "status": "playing",
-                                "time": current_playtime + 1
-                            }),
-                        ) {
-                            error!("Error happened while emitting playtime: {}", e);
-                        }
-                    }
-                    None => {
-                        info!("Game process not found, stopping playtime tracking");
+        if accruing {
+            self.credit(&snapshot, elapsed, false);
+        }
+    }
 
-                        if let Err(e) = store.update_playtime(&game_id, current_playtime % 60) {
-                            error!("Error happened while setting new playtime: {}", e);
-                        }
+    /// Looks for a process taking over from the exited one and ends tracking after the grace period,
+    /// returns false once tracking has ended
+    fn handle_missing_process(&mut self) -> bool {
+        if let Some(new_pid) = SystemService::find_replacement_process(&self.game) {
+            info!(
+                "Game {} continues as PID {}, re-attaching",
+                self.game_id, new_pid
+            );
+            self.reattach(new_pid.as_u32());
+            self.missing_since = None;
+            return true;
+        }
 
-                        if let Err(e) = store.update_last_played(&game_id) {
-                            error!("Error happened while updating last played: {}", e);
-                        }
+        let missing_since = *self.missing_since.get_or_insert_with(Instant::now);
+        if missing_since.elapsed() < REATTACH_GRACE {
+            debug!("Game process not found, waiting for a replacement");
+            return true;
+        }
 
-                        let state = app_handle.state::<ManagedState>();
-                        let mut state = match state.lock() {
-                            Ok(s) => s,
-                            Err(e) => {
-                                error!("Failed to acquire app state mutex lock: {}", e);
-                                return;
-                            }
-                        };
-                        state.game = None;
+        info!("Game process not found, stopping playtime tracking");
+        self.finish();
+        false
+    }
 
-                        if let Some(pres) = &mut state.presence {
-                            if let Err(e) = pres.reset_presence() {
-                                error!("Error happened while clearing presence: {}", e);
-                            }
-                        }
+    /// Points a running game's state at a new process
+    fn reattach(&self, pid: u32) {
+        let state = self.app_handle.state::<ManagedState>();
+        let mut state = match state.lock() {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Failed to acquire app state mutex lock: {}", e);
+                return;
+            }
+        };
 
-                        if let Err(e) = app_handle.emit("current_game", serde_json::json!(null)) {
-                            error!("Error happened while emitting current_game event: {}", e);
-                        }
-                        break;
+        if let Some(tracked) = state.games.get_mut(&self.game_id) {
+            tracked.pid = pid;
+        }
+
+        GameManager::emit_current_game(&self.app_handle, &state);
+    }
+
+    /// Flushes the remaining playtime, closes the session and stops tracking the game
+    fn finish(&self) {
+        let Some(snapshot) = self.snapshot() else {
+            return;
+        };
+        let game_state = &snapshot.game_state;
+        let game_id = &self.game_id;
+
+        let unflushed = game_state
+            .current_playtime
+            .saturating_sub(game_state.flushed_playtime);
+        if let Err(e) = self
+            .store
+            .update_playtime(game_id, unflushed, &snapshot.day)
+        {
+            error!("Error happened while setting new playtime: {}", e);
+        }
+
+        if let Err(e) = self.store.update_last_played(game_id) {
+            error!("Error happened while updating last played: {}", e);
+        }
+
+        GoalTracker::evaluate(&self.app_handle);
+
+        if let Some(session_id) = &game_state.session_id {
+            let chars_read = self
+                .store
+                .get(game_id)
+                .map(|g| g.chars_read)
+                .unwrap_or_default();
+
+            if let Err(e) = SessionsStore::new(&self.app_handle).and_then(|sessions| {
+                sessions.end(
+                    session_id,
+                    game_state.current_playtime,
+                    chars_read,
+                    util::unix_now(),
+                )
+            }) {
+                error!("Error happened while ending session: {}", e);
+            }
+        }
+
+        let state = self.app_handle.state::<ManagedState>();
+        let mut state = match state.lock() {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Failed to acquire app state mutex lock: {}", e);
+                return;
+            }
+        };
+        state.games.remove(game_id);
+
+        if let Err(e) = self.journal.write(&state.games) {
+            error!("Error happened while writing session journal: {}", e);
+        }
+
+        // Hand presence over to another running game, if any
+        let next_game = state
+            .latest_game()
+            .and_then(|g| Some((g.id.clone(), self.store.get(&g.id)?)));
+
+        match next_game {
+            Some((next_id, next)) => {
+                GameManager::show_presence(&mut state, &next_id, &next);
+            }
+            None => {
+                if let Some(pres) = &mut state.presence {
+                    if let Err(e) = pres.reset_presence() {
+                        error!("Error happened while clearing presence: {}", e);
                     }
                 }
             }
-        });
+        }
+
+        GameManager::emit_current_game(&self.app_handle, &state);
     }
 }
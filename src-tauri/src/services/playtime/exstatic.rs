@@ -1,8 +1,15 @@
 use crate::{
     prelude::Result,
     services::{
+        goals::GoalTracker,
+        journal::SessionJournal,
         state::ManagedState,
-        stores::{games::GamesStore, settings::PlaytimeMode},
+        stats,
+        stores::{
+            games::{GamesRepository, GamesStore, Progress},
+            sessions::SessionsStore,
+            settings::PlaytimeMode,
+        },
         system::SystemService,
     },
 };
@@ -10,12 +17,15 @@ use anyhow::Context;
 use futures_util::{SinkExt, StreamExt};
 use log::{debug, error, info, warn};
 use serde::Deserialize;
+use std::time::Duration;
 use tauri::{AppHandle, Emitter, Manager};
 use tokio::net::{TcpListener, TcpStream};
 use tokio_tungstenite::accept_async;
 use tokio_util::sync::CancellationToken;
 
 const SERVER_ADDRESS: &str = "127.0.0.1:6969";
+/// How often the reading goals are evaluated while data comes in
+const GOALS_INTERVAL: Duration = Duration::from_secs(60);
 
 #[derive(Deserialize, Debug)]
 struct ExStaticData {
@@ -32,57 +42,128 @@ impl ExStaticPlaytime {
         serde_json::from_str(&input).context("Failed to deserialize exstatic input")
     }
 
+    /// Current progress of a game, using the library's recent speed when the game has none
+    fn progress(app_handle: &AppHandle, store: &GamesStore, game_id: &str) -> Option<Progress> {
+        let game = store.get(game_id)?;
+
+        let fallback_speed = match stats::recent_speed([&game]) {
+            Some(_) => None,
+            None => app_handle
+                .state::<GamesRepository>()
+                .read(|games| stats::recent_speed(games.values()))
+                .ok()
+                .flatten(),
+        };
+
+        stats::progress(&game, fallback_speed)
+    }
+
     fn handle(app_handle: &AppHandle, data: ExStaticData) -> Result<()> {
         debug!("Handling ExStatic data: {:?}", data);
 
+        let Some(pid) = SystemService::get_pid_from_process_path(&data.process_path) else {
+            debug!("PID not found, ignoring data");
+            return Ok(());
+        };
+
+        // The state is only locked to read and update the running game,
+        // store and session work happens without holding it
         let managed = app_handle.state::<ManagedState>();
-        let mut state = managed
-            .lock()
-            .map_err(|e| anyhow::anyhow!("Error acquiring mutex lock: {}", e))?;
+        let (game_id, day) = {
+            let mut state = managed.lock()?;
+            let is_exstatic = matches!(&state.settings.playtime_mode, PlaytimeMode::ExStatic);
+            let day = state.settings.day_boundary();
 
-        if !matches!(&state.settings.playtime_mode, PlaytimeMode::ExStatic) {
-            debug!("PlaytimeMode is not ExStatic, ignoring data");
-            return Ok(());
+            let Some(game) = state.game_by_pid_mut(pid.as_u32()) else {
+                warn!("No running game with PID {}, ignoring data", pid.as_u32());
+                return Ok(());
+            };
+
+            // Incoming text means the game is being read, whichever mode tracks playtime
+            game.mark_activity();
+
+            if !is_exstatic {
+                debug!("PlaytimeMode is not ExStatic, ignoring data");
+                return Ok(());
+            }
+
+            if game.paused {
+                debug!("Tracking of game {} is paused, ignoring data", game.id);
+                return Ok(());
+            }
+
+            (game.id.clone(), day)
+        };
+
+        let time = data.time.round() as u64;
+        let store = GamesStore::new(app_handle)?;
+        info!("Updating playtime for game {} by {} seconds", game_id, time);
+        store.update_playtime(&game_id, time, &day)?;
+
+        // Credited to the running game only once it's in the store, so stopping it
+        // in between doesn't credit the time twice
+        let (current_playtime, session_id) = {
+            let mut state = managed.lock()?;
+            let Some(game) = state.games.get_mut(&game_id) else {
+                debug!("Game {} stopped while handling data", game_id);
+                return Ok(());
+            };
+            game.current_playtime += time;
+            game.flushed_playtime += time;
+            let running = (game.current_playtime, game.session_id.clone());
+
+            if let Err(e) = SessionJournal::new(app_handle).and_then(|j| j.write(&state.games)) {
+                error!("Error happened while writing session journal: {}", e);
+            }
+            running
+        };
+
+        // Update chars_read if provided by exSTATic
+        if let Some(chars_read) = data.chars_read {
+            debug!("Updating chars_read for game {} to {}", game_id, chars_read);
+            store.update_chars_read(&game_id, chars_read, time, &day)?;
         }
 
-        if let (Some(game), Some(pid)) = (
-            state.game.as_mut(),
-            SystemService::get_pid_from_process_path(&data.process_path),
-        ) {
-            if pid.as_u32() == game.pid {
-                let time = data.time.round() as u64;
-                let store = GamesStore::new(app_handle)?;
-                info!("Updating playtime for game {} by {} seconds", game.id, time);
-                game.current_playtime += time;
-                store.update_playtime(&game.id, time)?;
-
-                // Update chars_read if provided by exSTATic
-                if let Some(chars_read) = data.chars_read {
-                    debug!("Updating chars_read for game {} to {}", game.id, chars_read);
-                    store.update_game(&game.id, |g| g.chars_read = chars_read)?;
-                    if let Err(e) = app_handle.emit("chars_read_updated", chars_read) {
-                        error!("Error emitting chars_read_updated event: {}", e);
-                    }
-                }
+        // The game's total, which also counts characters from manual sessions
+        let chars_read = store
+            .get(&game_id)
+            .map(|g| g.chars_read)
+            .unwrap_or_default();
+
+        let mut live_speed = None;
+        if let Some(session_id) = &session_id {
+            let sessions = SessionsStore::new(app_handle)?;
+            sessions.record_progress(session_id, current_playtime, chars_read)?;
+            live_speed = sessions.get(session_id)?.chars_per_hour;
+        }
 
-                if let Err(e) = app_handle.emit(
-                    "playtime",
-                    serde_json::json!({
-                        "status": "playing",
-                        "time": game.current_playtime
-                    }),
-                ) {
-                    error!("Error happened while emitting playtime: {}", e);
+        if data.chars_read.is_some() {
+            let payload = serde_json::json!({
+                "id": game_id,
+                "chars_read": chars_read,
+                "chars_per_hour": live_speed
+            });
+
+            if let Err(e) = app_handle.emit("chars_read_updated", payload) {
+                error!("Error emitting chars_read_updated event: {}", e);
+            }
+
+            if let Some(progress) = Self::progress(app_handle, &store, &game_id) {
+                let payload = serde_json::json!({ "id": game_id, "progress": progress });
+                if let Err(e) = app_handle.emit("progress_updated", payload) {
+                    error!("Error emitting progress_updated event: {}", e);
                 }
-            } else {
-                warn!(
-                    "PID mismatch: data PID {} != game PID {}",
-                    pid.as_u32(),
-                    game.pid
-                );
             }
-        } else {
-            debug!("No game running or PID not found, ignoring data");
+        }
+
+        let payload = serde_json::json!({
+            "id": game_id,
+            "status": "playing",
+            "time": current_playtime
+        });
+
+        if let Err(e) = app_handle.emit("playtime", payload) {
+            error!("Error happened while emitting playtime: {}", e);
         }
 
         Ok(())
@@ -148,6 +229,8 @@ impl ExStaticPlaytime {
         };
 
         let (mut write, mut read) = ws_stream.split();
+        let mut goals_tick = tokio::time::interval(GOALS_INTERVAL);
+        let mut goals_dirty = false;
         loop {
             tokio::select! {
                 _ = conn_token.cancelled() => {
@@ -155,6 +238,12 @@ impl ExStaticPlaytime {
                     let _ = write.send(tokio_tungstenite::tungstenite::Message::Close(None)).await;
                     break;
                 }
+                _ = goals_tick.tick() => {
+                    if goals_dirty {
+                        goals_dirty = false;
+                        GoalTracker::evaluate(&app_handle);
+                    }
+                }
                 message = read.next() => {
                     match message {
                         Some(Ok(msg)) => {
@@ -162,8 +251,9 @@ impl ExStaticPlaytime {
                                 let msg_text = msg.into_text().expect("already checked for text");
                                 match Self::process_input(msg_text.to_string()) {
                                     Ok(data) => {
-                                        if let Err(e) = Self::handle(&app_handle, data) {
-                                            error!("Error handling ExStatic data: {}", e);
+                                        match Self::handle(&app_handle, data) {
+                                            Ok(()) => goals_dirty = true,
+                                            Err(e) => error!("Error handling ExStatic data: {}", e),
                                         }
                                     }
                                     Err(e) => error!("Error processing input: {}", e),
@@ -179,6 +269,10 @@ impl ExStaticPlaytime {
                 }
             }
         }
+
+        if goals_dirty {
+            GoalTracker::evaluate(&app_handle);
+        }
         info!("WebSocket connection closed.");
     }
 }
@@ -0,0 +1,383 @@
+use crate::{
+    services::{
+        stats::{self, Period, PeriodTotals, Speed},
+        stores::{
+            games::{Game, Games},
+            sessions::Sessions,
+        },
+    },
+    util::{self, DayBoundary},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt::Write, fs, path::Path};
+
+/// Number of games listed in `Report::top_games`
+const TOP_GAMES: usize = 10;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFormat {
+    /// A single self-contained page with the covers embedded
+    Html,
+    Markdown,
+}
+
+/// A game's share of the reported period
+#[derive(Serialize, Debug)]
+pub struct ReportGame {
+    pub id: String,
+    pub title: String,
+    /// Play time in seconds
+    pub playtime: u64,
+    pub chars_read: u64,
+    /// Path of the cached cover image
+    pub cover: Option<String>,
+}
+
+impl ReportGame {
+    fn new(id: &str, game: &Game, period: &Period) -> Self {
+        let in_period = |ledger: &BTreeMap<String, u64>| -> u64 {
+            ledger
+                .iter()
+                .filter(|(date, _)| period.contains(date))
+                .map(|(_, value)| value)
+                .sum()
+        };
+
+        Self {
+            id: id.to_string(),
+            title: game.title.clone(),
+            playtime: in_period(&game.daily_playtime),
+            chars_read: in_period(&game.daily_chars),
+            cover: (!game.image_url.is_empty()).then(|| game.image_url.clone()),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct BusiestDay {
+    /// `YYYY-MM-DD`
+    pub date: String,
+    /// Play time in seconds
+    pub playtime: u64,
+    pub chars_read: u64,
+}
+
+/// Summary of the reading done over an inclusive range of reading days
+#[derive(Serialize, Debug)]
+pub struct Report {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub totals: PeriodTotals,
+    /// Reading speed over the whole period
+    pub speed: Speed,
+    /// Reading speed over the first half of the period's days with timed reading
+    pub early_speed: Option<u64>,
+    /// Reading speed over the second half of the period's days with timed reading
+    pub late_speed: Option<u64>,
+    /// Games with the most playtime in the period, most first
+    pub top_games: Vec<ReportGame>,
+    /// Completed games last played in the period
+    pub finished: Vec<ReportGame>,
+    /// Day with the most playtime
+    pub busiest_day: Option<BusiestDay>,
+}
+
+impl Report {
+    /// Builds the report from the games' daily ledgers and their sessions
+    pub fn new(games: &Games, sessions: &Sessions, day: &DayBoundary, period: &Period) -> Self {
+        let stats = stats::stats(games, sessions, day, period);
+
+        let mut top_games: Vec<ReportGame> = games
+            .iter()
+            .map(|(id, game)| ReportGame::new(id, game, period))
+            .filter(|g| g.playtime > 0 || g.chars_read > 0)
+            .collect();
+        top_games.sort_by(|a, b| {
+            b.playtime
+                .cmp(&a.playtime)
+                .then(b.chars_read.cmp(&a.chars_read))
+        });
+        top_games.truncate(TOP_GAMES);
+
+        let finished = stats
+            .games_finished
+            .iter()
+            .filter_map(|id| Some(ReportGame::new(id, games.get(id)?, period)))
+            .collect();
+
+        let busiest_day = stats
+            .daily
+            .iter()
+            .max_by_key(|(_, total)| (total.playtime, total.chars_read))
+            .map(|(date, total)| BusiestDay {
+                date: date.clone(),
+                playtime: total.playtime,
+                chars_read: total.chars_read,
+            });
+
+        let reading_speed = stats::reading_speed(games, sessions);
+        let timed_days: Vec<&Speed> = reading_speed
+            .daily
+            .iter()
+            .filter(|(date, _)| period.contains(date))
+            .map(|(_, speed)| speed)
+            .collect();
+        let (early, late) = timed_days.split_at(timed_days.len() / 2);
+
+        Self {
+            from: period.from.map(str::to_string),
+            to: period.to.map(str::to_string),
+            totals: stats.totals,
+            speed: total_speed(&timed_days),
+            early_speed: total_speed(early).chars_per_hour,
+            late_speed: total_speed(late).chars_per_hour,
+            top_games,
+            finished,
+            busiest_day,
+        }
+    }
+
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Html => self.to_html(),
+            ReportFormat::Markdown => self.to_markdown(),
+        }
+    }
+
+    fn period_label(&self) -> String {
+        match (&self.from, &self.to) {
+            (Some(from), Some(to)) => format!("{} to {}", from, to),
+            (Some(from), None) => format!("Since {}", from),
+            (None, Some(to)) => format!("Until {}", to),
+            (None, None) => "All time".to_string(),
+        }
+    }
+
+    /// Labelled headline figures shared by every format
+    fn summary(&self) -> Vec<(&'static str, String)> {
+        let mut summary = vec![
+            ("Time read", format_duration(self.totals.playtime)),
+            ("Characters read", thousands(self.totals.chars_read)),
+            ("Games played", self.totals.games_played.to_string()),
+            ("Sessions", self.totals.sessions.to_string()),
+            ("Active days", self.totals.active_days.to_string()),
+        ];
+
+        if let Some(speed) = self.speed.chars_per_hour {
+            summary.push(("Reading speed", format!("{} chars/h", thousands(speed))));
+        }
+
+        if let (Some(early), Some(late)) = (self.early_speed, self.late_speed) {
+            let change = (late as f64 / early.max(1) as f64 - 1.0) * 100.0;
+            summary.push((
+                "Speed improvement",
+                format!(
+                    "{} → {} chars/h ({:+.0}%)",
+                    thousands(early),
+                    thousands(late),
+                    change
+                ),
+            ));
+        }
+
+        if let Some(day) = &self.busiest_day {
+            summary.push((
+                "Busiest day",
+                format!(
+                    "{} ({}, {} chars)",
+                    day.date,
+                    format_duration(day.playtime),
+                    thousands(day.chars_read)
+                ),
+            ));
+        }
+
+        summary
+    }
+
+    fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# Reading report\n\n**{}**\n", self.period_label());
+
+        out.push_str("## Summary\n\n");
+        for (label, value) in self.summary() {
+            let _ = writeln!(out, "- {}: {}", label, value);
+        }
+
+        if !self.top_games.is_empty() {
+            out.push_str("\n## Top games by time\n\n");
+            out.push_str("| # | Game | Time | Characters |\n|---|---|---|---|\n");
+            for (rank, game) in self.top_games.iter().enumerate() {
+                let _ = writeln!(
+                    out,
+                    "| {} | {} | {} | {} |",
+                    rank + 1,
+                    escape_markdown(&game.title),
+                    format_duration(game.playtime),
+                    thousands(game.chars_read)
+                );
+            }
+        }
+
+        if !self.finished.is_empty() {
+            out.push_str("\n## Finished titles\n\n");
+            for game in &self.finished {
+                let _ = writeln!(
+                    out,
+                    "- {} ({})",
+                    escape_markdown(&game.title),
+                    format_duration(game.playtime)
+                );
+            }
+        }
+
+        out
+    }
+
+    fn to_html(&self) -> String {
+        let mut out = String::new();
+        out.push_str(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>Reading report</title>\n<style>\n\
+             body { font-family: sans-serif; max-width: 48rem; margin: 2rem auto; padding: 0 1rem; }\n\
+             table { border-collapse: collapse; width: 100%; }\n\
+             th, td { text-align: left; padding: 0.25rem 0.5rem; border-bottom: 1px solid #ddd; }\n\
+             .covers { display: flex; flex-wrap: wrap; gap: 1rem; }\n\
+             .covers figure { margin: 0; width: 8rem; }\n\
+             .covers img { width: 100%; border-radius: 4px; }\n\
+             </style>\n</head>\n<body>\n",
+        );
+        let _ = writeln!(
+            out,
+            "<h1>Reading report</h1>\n<p><strong>{}</strong></p>",
+            escape_html(&self.period_label())
+        );
+
+        out.push_str("<h2>Summary</h2>\n<ul>\n");
+        for (label, value) in self.summary() {
+            let _ = writeln!(out, "<li>{}: {}</li>", label, escape_html(&value));
+        }
+        out.push_str("</ul>\n");
+
+        if !self.top_games.is_empty() {
+            out.push_str("<h2>Top games by time</h2>\n<table>\n");
+            out.push_str("<tr><th>#</th><th>Game</th><th>Time</th><th>Characters</th></tr>\n");
+            for (rank, game) in self.top_games.iter().enumerate() {
+                let _ = writeln!(
+                    out,
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    rank + 1,
+                    escape_html(&game.title),
+                    format_duration(game.playtime),
+                    thousands(game.chars_read)
+                );
+            }
+            out.push_str("</table>\n");
+        }
+
+        if !self.finished.is_empty() {
+            out.push_str("<h2>Finished titles</h2>\n<div class=\"covers\">\n");
+            for game in &self.finished {
+                out.push_str("<figure>");
+                if let Some(src) = game.cover.as_deref().and_then(cover_data_uri) {
+                    let _ = write!(
+                        out,
+                        "<img src=\"{}\" alt=\"{}\">",
+                        src,
+                        escape_html(&game.title)
+                    );
+                }
+                let _ = writeln!(
+                    out,
+                    "<figcaption>{}<br>{}</figcaption></figure>",
+                    escape_html(&game.title),
+                    format_duration(game.playtime)
+                );
+            }
+            out.push_str("</div>\n");
+        }
+
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+fn total_speed(days: &[&Speed]) -> Speed {
+    let (chars_read, reading_secs) = days.iter().fold((0, 0), |(chars, secs), day| {
+        (chars + day.chars_read, secs + day.reading_secs)
+    });
+
+    Speed {
+        chars_read,
+        reading_secs,
+        chars_per_hour: util::chars_per_hour(chars_read, reading_secs),
+    }
+}
+
+/// Reads a cached cover into a `data:` URI, so the report doesn't depend on the images folder
+fn cover_data_uri(path: &str) -> Option<String> {
+    let path = Path::new(path);
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!("Failed to read cover image {:?}: {}", path, e);
+            return None;
+        }
+    };
+
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let mime = match extension.as_str() {
+        "png" => "image/png",
+        "webp" => "image/webp",
+        "gif" => "image/gif",
+        _ => "image/jpeg",
+    };
+
+    Some(format!("data:{};base64,{}", mime, STANDARD.encode(bytes)))
+}
+
+/// Formats seconds as hours and minutes, e.g. `12h 05m`
+fn format_duration(secs: u64) -> String {
+    format!("{}h {:02}m", secs / 3600, secs % 3600 / 60)
+}
+
+/// Formats a number with comma thousands separators
+fn thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            out.push(',');
+        }
+        out.push(c);
+    }
+
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '|' | '<' | '>') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
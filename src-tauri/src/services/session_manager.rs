@@ -0,0 +1,222 @@
+use crate::{
+    prelude::Result,
+    services::{
+        state::ManagedState,
+        stores::{
+            games::{Game, GamesStore},
+            sessions::{PlaySession, SessionsStore, TrackingSource},
+        },
+    },
+    util::{self, DayBoundary},
+};
+use anyhow::Context;
+use log::info;
+use serde::Deserialize;
+use tauri::{AppHandle, Manager};
+
+/// Details of a session logged by hand
+#[derive(Deserialize, Debug)]
+pub struct ManualSessionInput {
+    /// Unix timestamp (seconds) of when reading started, decides the reading day
+    pub started_at: u64,
+    /// Seconds read
+    pub duration_secs: u64,
+    /// Characters read during the session
+    #[serde(default)]
+    pub chars_read: u64,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+impl ManualSessionInput {
+    fn validate(&self) -> Result<()> {
+        anyhow::ensure!(
+            self.duration_secs > 0 || self.chars_read > 0,
+            "Session must have a duration or characters read"
+        );
+        anyhow::ensure!(
+            self.started_at.saturating_add(self.duration_secs) <= util::unix_now(),
+            "Session can't end in the future"
+        );
+        Ok(())
+    }
+
+    /// Writes the input into a session, keeping its id, game and starting char count
+    fn apply_to(self, session: &mut PlaySession) {
+        session.started_at = self.started_at;
+        session.ended_at = Some(self.started_at + self.duration_secs);
+        session.active_secs = self.duration_secs;
+        session.chars_read_end = session.chars_read_start + self.chars_read;
+        session.note = self.note.filter(|n| !n.trim().is_empty());
+        session.refresh_speed();
+    }
+}
+
+/// Logs, edits and deletes sessions while keeping the game's stats in sync with them
+pub struct SessionManager<'a> {
+    app_handle: &'a AppHandle,
+}
+
+impl<'a> SessionManager<'a> {
+    pub fn new(app_handle: &'a AppHandle) -> Self {
+        Self { app_handle }
+    }
+
+    fn day_boundary(&self) -> Result<DayBoundary> {
+        let managed = self.app_handle.state::<ManagedState>();
+        let state = managed.lock()?;
+        Ok(state.settings.day_boundary())
+    }
+
+    /// Logs a session for reading Tadoku couldn't track and credits it to the game
+    pub fn create_manual(&self, game_id: &str, input: ManualSessionInput) -> Result<PlaySession> {
+        input.validate()?;
+        let day = self.day_boundary()?;
+        let games = GamesStore::new(self.app_handle)?;
+        let sessions = SessionsStore::new(self.app_handle)?;
+
+        let game = games
+            .get(game_id)
+            .context(format!("Game with id {} not found", game_id))?;
+
+        let mut session = PlaySession {
+            id: sessions.next_id(&format!("{}-manual-{}", game_id, input.started_at))?,
+            game_id: game_id.to_string(),
+            started_at: input.started_at,
+            ended_at: None,
+            active_secs: 0,
+            chars_read_start: game.chars_read,
+            chars_read_end: game.chars_read,
+            source: TrackingSource::Manual,
+            pauses: Vec::new(),
+            note: None,
+            chars_per_hour: None,
+            first_played_before: game.first_played,
+            last_played_before: game.last_played,
+        };
+        input.apply_to(&mut session);
+
+        info!(
+            "Logging manual session for game {}: {}s, {} chars",
+            game_id,
+            session.active_secs,
+            session.chars_read()
+        );
+        sessions.insert(session.clone())?;
+        games.update_game(game_id, |g| Self::credit(g, &session, &day))?;
+
+        Ok(session)
+    }
+
+    /// Edits a manual session, moving its contribution to the game's stats along with it
+    pub fn update_manual(
+        &self,
+        session_id: &str,
+        input: ManualSessionInput,
+    ) -> Result<PlaySession> {
+        input.validate()?;
+        let day = self.day_boundary()?;
+        let games = GamesStore::new(self.app_handle)?;
+        let sessions = SessionsStore::new(self.app_handle)?;
+
+        let old = sessions.get(session_id)?;
+        anyhow::ensure!(
+            old.source == TrackingSource::Manual,
+            "Only manual sessions can be edited"
+        );
+
+        let mut session = old.clone();
+        input.apply_to(&mut session);
+
+        info!("Updating manual session: {}", session_id);
+        sessions.replace(session.clone())?;
+
+        let others = Self::other_sessions(&sessions, &session)?;
+        games.update_game(&session.game_id, |g| {
+            Self::revoke(g, &old, &day, &others);
+            Self::credit(g, &session, &day);
+        })?;
+
+        Ok(session)
+    }
+
+    /// Deletes a session. Manual sessions are also taken back from the game's stats,
+    /// tracked ones leave them untouched.
+    pub fn delete(&self, session_id: &str) -> Result<()> {
+        let sessions = SessionsStore::new(self.app_handle)?;
+        let session = sessions.get(session_id)?;
+        sessions.delete(session_id)?;
+
+        if session.source == TrackingSource::Manual {
+            let day = self.day_boundary()?;
+            let others = Self::other_sessions(&sessions, &session)?;
+            GamesStore::new(self.app_handle)?.update_game(&session.game_id, |g| {
+                Self::revoke(g, &session, &day, &others)
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn other_sessions(sessions: &SessionsStore, session: &PlaySession) -> Result<Vec<PlaySession>> {
+        Ok(sessions
+            .get_by_game(&session.game_id)?
+            .into_iter()
+            .filter(|s| s.id != session.id)
+            .collect())
+    }
+
+    fn credit(game: &mut Game, session: &PlaySession, day: &DayBoundary) {
+        let date = day.date_of(session.started_at);
+        game.add_playtime_on(session.active_secs, &date);
+        game.pin_exstatic_chars();
+        let chars = session.chars_read();
+        if session.active_secs == 0 {
+            game.add_untimed_chars_on(chars, &date);
+        } else {
+            game.add_chars_on(chars, &date);
+            if chars > 0 {
+                game.add_reading_on(session.active_secs, &date);
+            }
+        }
+
+        let ended_at = session.ended_at.unwrap_or(session.started_at);
+        game.first_played = Some(
+            game.first_played
+                .map_or(session.started_at, |first| first.min(session.started_at)),
+        );
+        game.last_played = Some(game.last_played.map_or(ended_at, |last| last.max(ended_at)));
+    }
+
+    /// Takes a session back from a game's stats. When it set the first or last played time,
+    /// those fall back to their value before the session or the game's other sessions.
+    fn revoke(game: &mut Game, session: &PlaySession, day: &DayBoundary, others: &[PlaySession]) {
+        let date = day.date_of(session.started_at);
+        game.remove_playtime_on(session.active_secs, &date);
+        game.pin_exstatic_chars();
+        let chars = session.chars_read();
+        if session.active_secs == 0 {
+            game.remove_untimed_chars_on(chars, &date);
+        } else {
+            game.remove_chars_on(chars, &date);
+            if chars > 0 {
+                game.remove_reading_on(session.active_secs, &date);
+            }
+        }
+
+        if game.first_played == Some(session.started_at) {
+            game.first_played = others
+                .iter()
+                .map(|s| s.started_at)
+                .chain(session.first_played_before)
+                .min();
+        }
+        if game.last_played == session.ended_at {
+            game.last_played = others
+                .iter()
+                .map(|s| s.ended_at.unwrap_or(s.started_at))
+                .chain(session.last_played_before)
+                .max();
+        }
+    }
+}
@@ -2,27 +2,71 @@ use crate::{
     prelude::Result,
     services::{
         discord::DiscordPresence,
-        stores::settings::{Settings, SettingsStore},
+        stores::{
+            file::CorruptStore,
+            settings::{Settings, SettingsStore},
+        },
     },
 };
-use std::sync::{Mutex, MutexGuard};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Mutex, MutexGuard},
+};
 use tauri::AppHandle;
 
 #[derive(Default, Clone)]
 pub struct GameState {
     pub id: String,
     pub pid: u32,
+    /// Unix timestamp (seconds) of when tracking started
+    pub started_at: u64,
     pub current_playtime: u64,
+    /// Seconds of `current_playtime` already written to the games store
+    pub flushed_playtime: u64,
+    /// Id of the `PlaySession` opened for this run
+    pub session_id: Option<String>,
+    /// Tracking was paused manually, no playtime is credited until resumed
+    pub paused: bool,
+    /// Seconds credited since the last text or window activity,
+    /// taken back once they exceed the idle timeout
+    pub idle_secs: u64,
+    /// No activity was seen for the idle timeout, no playtime is credited until there is
+    pub idle: bool,
+}
+
+impl GameState {
+    /// Records reader activity, confirming the playtime credited since the last one
+    pub fn mark_activity(&mut self) {
+        self.idle_secs = 0;
+        self.idle = false;
+    }
 }
 
 #[derive(Default)]
 pub struct AppState {
-    pub game: Option<GameState>,
+    /// Running games keyed by game id
+    pub games: HashMap<String, GameState>,
     pub presence: Option<DiscordPresence>,
     pub settings: Settings,
+    /// Reading goals already met, keyed by goal id and period start
+    pub reached_goals: HashSet<String>,
+    /// Store files found unreadable on startup and not restored yet
+    pub corrupt_stores: Vec<CorruptStore>,
+    /// Set while `store.json` holds a library that wasn't imported into the database
+    pub store_notice: Option<String>,
 }
 
 impl AppState {
+    /// Most recently started running game
+    pub fn latest_game(&self) -> Option<&GameState> {
+        self.games.values().max_by_key(|g| g.started_at)
+    }
+
+    /// Running game whose tracked process has the given PID
+    pub fn game_by_pid_mut(&mut self, pid: u32) -> Option<&mut GameState> {
+        self.games.values_mut().find(|g| g.pid == pid)
+    }
+
     pub fn update_settings<F>(&mut self, app_handle: &AppHandle, update_fn: F) -> Result<()>
     where
         F: FnOnce(&mut Settings),
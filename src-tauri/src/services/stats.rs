@@ -0,0 +1,332 @@
+use crate::{
+    prelude::Fetchable,
+    services::stores::{
+        games::{DailyTotal, Game, Games, Progress},
+        sessions::Sessions,
+    },
+    util::{self, DayBoundary},
+};
+use chrono::{Datelike, NaiveDate, TimeDelta};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Most recent days with timed reading that make up the recent reading speed
+const RECENT_READING_DAYS: usize = 14;
+/// Number of sessions listed in `Stats::longest_sessions`
+const LONGEST_SESSIONS: usize = 10;
+/// Category marking a game as read to the end
+const COMPLETED_CATEGORY: &str = "Completed";
+
+/// Characters read over a stretch of timed reading
+#[derive(Serialize, Debug, Default, Clone, Copy)]
+pub struct Speed {
+    pub chars_read: u64,
+    pub reading_secs: u64,
+    pub chars_per_hour: Option<u64>,
+}
+
+impl Speed {
+    fn add(&mut self, chars_read: u64, reading_secs: u64) {
+        self.chars_read += chars_read;
+        self.reading_secs += reading_secs;
+        self.chars_per_hour = util::chars_per_hour(self.chars_read, self.reading_secs);
+    }
+}
+
+/// Reading speed of a single session
+#[derive(Serialize, Debug)]
+pub struct SessionSpeed {
+    pub session_id: String,
+    pub game_id: String,
+    pub started_at: u64,
+    pub chars_per_hour: u64,
+}
+
+/// Reading speed trends, only days and sessions with timed reading are included
+#[derive(Serialize, Debug, Default)]
+pub struct ReadingSpeed {
+    pub overall: Speed,
+    /// Keyed by `YYYY-MM-DD`
+    pub daily: BTreeMap<String, Speed>,
+    /// Keyed by game id
+    pub games: BTreeMap<String, Speed>,
+    /// Ordered by start time
+    pub sessions: Vec<SessionSpeed>,
+}
+
+/// Builds reading speed trends from the games' ledgers and their sessions
+pub fn reading_speed(games: &Games, sessions: &Sessions) -> ReadingSpeed {
+    let mut speed = ReadingSpeed::default();
+
+    for (game_id, game) in games {
+        for (date, reading_secs) in &game.daily_reading_secs {
+            let chars = game.timed_chars_on(date);
+
+            speed.overall.add(chars, *reading_secs);
+            speed
+                .daily
+                .entry(date.clone())
+                .or_default()
+                .add(chars, *reading_secs);
+            speed
+                .games
+                .entry(game_id.clone())
+                .or_default()
+                .add(chars, *reading_secs);
+        }
+    }
+
+    speed.sessions = sessions
+        .iter()
+        .filter(|s| games.contains_key(&s.game_id))
+        .filter_map(|s| {
+            Some(SessionSpeed {
+                session_id: s.id.clone(),
+                game_id: s.game_id.clone(),
+                started_at: s.started_at,
+                chars_per_hour: s.chars_per_hour?,
+            })
+        })
+        .collect();
+
+    speed
+}
+
+/// Reading speed over the most recent days with timed reading of the given games
+pub fn recent_speed<'a>(games: impl IntoIterator<Item = &'a Game>) -> Option<u64> {
+    let mut days: BTreeMap<&str, (u64, u64)> = BTreeMap::new();
+
+    for game in games {
+        for (date, reading_secs) in &game.daily_reading_secs {
+            let day = days.entry(date.as_str()).or_default();
+            day.0 += game.timed_chars_on(date);
+            day.1 += reading_secs;
+        }
+    }
+
+    let (chars, secs) = days
+        .values()
+        .rev()
+        .take(RECENT_READING_DAYS)
+        .fold((0, 0), |(chars, secs), (day_chars, day_secs)| {
+            (chars + day_chars, secs + day_secs)
+        });
+
+    util::chars_per_hour(chars, secs)
+}
+
+/// A game's progress, estimated with its own recent speed or `fallback_speed` when it has none
+pub fn progress(game: &Game, fallback_speed: Option<u64>) -> Option<Progress> {
+    let Fetchable::Available(total_chars) = game.jiten_char_count else {
+        return None;
+    };
+
+    let speed = recent_speed([game]).or(fallback_speed);
+    Progress::new(game.chars_read, total_chars, speed)
+}
+
+/// Fills in the progress of every game, falling back to the library-wide recent speed
+pub fn fill_progress(games: &mut Games) {
+    let library_speed = recent_speed(games.values());
+
+    for game in games.values_mut() {
+        game.progress = progress(game, library_speed);
+    }
+}
+
+/// Totals over a whole period
+#[derive(Serialize, Debug, Default)]
+pub struct PeriodTotals {
+    /// Play time in seconds
+    pub playtime: u64,
+    pub chars_read: u64,
+    /// Games with playtime or characters read in the period
+    pub games_played: usize,
+    pub sessions: usize,
+    /// Days with any playtime or characters read
+    pub active_days: usize,
+}
+
+/// Library-wide statistics over an inclusive range of reading days
+#[derive(Serialize, Debug, Default)]
+pub struct Stats {
+    pub totals: PeriodTotals,
+    /// Keyed by `YYYY-MM-DD`
+    pub daily: BTreeMap<String, DailyTotal>,
+    /// Keyed by ISO week, `YYYY-Www`
+    pub weekly: BTreeMap<String, DailyTotal>,
+    /// Keyed by `YYYY-MM`
+    pub monthly: BTreeMap<String, DailyTotal>,
+    /// Keyed by `YYYY`
+    pub yearly: BTreeMap<String, DailyTotal>,
+    /// Keyed by category, a game in several categories counts towards each of them
+    pub categories: BTreeMap<String, DailyTotal>,
+    pub nsfw: DailyTotal,
+    pub sfw: DailyTotal,
+    /// Ids of games first played in the period
+    pub games_started: Vec<String>,
+    /// Ids of completed games last played in the period
+    pub games_finished: Vec<String>,
+    /// Longest sessions started in the period, longest first
+    pub longest_sessions: Sessions,
+    /// Consecutive reading days up to today (or yesterday, while today has no reading yet),
+    /// over the whole history
+    pub current_streak: u32,
+    /// Most consecutive reading days over the whole history
+    pub longest_streak: u32,
+}
+
+/// Inclusive range of reading days, `YYYY-MM-DD`, open ended when a bound is `None`
+pub struct Period<'a> {
+    pub from: Option<&'a str>,
+    pub to: Option<&'a str>,
+}
+
+impl Period<'_> {
+    pub fn contains(&self, date: &str) -> bool {
+        self.from.is_none_or(|from| date >= from) && self.to.is_none_or(|to| date <= to)
+    }
+}
+
+fn add(total: &mut DailyTotal, playtime: u64, chars_read: u64) {
+    total.playtime += playtime;
+    total.chars_read += chars_read;
+}
+
+/// Playtime and chars read of a game per reading day
+fn game_days(game: &Game) -> BTreeMap<&str, (u64, u64)> {
+    let mut days: BTreeMap<&str, (u64, u64)> = BTreeMap::new();
+
+    for (date, playtime) in &game.daily_playtime {
+        days.entry(date.as_str()).or_default().0 += playtime;
+    }
+    for (date, chars) in &game.daily_chars {
+        days.entry(date.as_str()).or_default().1 += chars;
+    }
+
+    days
+}
+
+/// Aggregates statistics over a period from the games' daily ledgers and the sessions
+pub fn stats(games: &Games, sessions: &Sessions, day: &DayBoundary, period: &Period) -> Stats {
+    let mut stats = Stats::default();
+    let mut reading_days = BTreeSet::new();
+
+    for (game_id, game) in games {
+        let mut game_total = DailyTotal::default();
+
+        for (date, (playtime, chars)) in game_days(game) {
+            if playtime == 0 && chars == 0 {
+                continue;
+            }
+            reading_days.insert(date.to_string());
+
+            if !period.contains(date) {
+                continue;
+            }
+            add(&mut game_total, playtime, chars);
+            add(
+                stats.daily.entry(date.to_string()).or_default(),
+                playtime,
+                chars,
+            );
+
+            if let Ok(parsed) = NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+                let week = parsed.iso_week();
+                let week_key = format!("{}-W{:02}", week.year(), week.week());
+                add(stats.weekly.entry(week_key).or_default(), playtime, chars);
+                add(
+                    stats.monthly.entry(date[..7].to_string()).or_default(),
+                    playtime,
+                    chars,
+                );
+                add(
+                    stats.yearly.entry(date[..4].to_string()).or_default(),
+                    playtime,
+                    chars,
+                );
+            }
+        }
+
+        if game_total.playtime > 0 || game_total.chars_read > 0 {
+            stats.totals.games_played += 1;
+            stats.totals.playtime += game_total.playtime;
+            stats.totals.chars_read += game_total.chars_read;
+
+            let split = if game.is_nsfw {
+                &mut stats.nsfw
+            } else {
+                &mut stats.sfw
+            };
+            add(split, game_total.playtime, game_total.chars_read);
+
+            for category in &game.categories {
+                let total = stats.categories.entry(category.clone()).or_default();
+                add(total, game_total.playtime, game_total.chars_read);
+            }
+        }
+
+        if game
+            .first_played
+            .is_some_and(|at| period.contains(&day.date_of(at)))
+        {
+            stats.games_started.push(game_id.clone());
+        }
+
+        let completed = game.categories.iter().any(|c| c == COMPLETED_CATEGORY);
+        if completed
+            && game
+                .last_played
+                .is_some_and(|at| period.contains(&day.date_of(at)))
+        {
+            stats.games_finished.push(game_id.clone());
+        }
+    }
+
+    stats.totals.active_days = stats.daily.len();
+
+    let mut period_sessions: Sessions = sessions
+        .iter()
+        .filter(|s| games.contains_key(&s.game_id))
+        .filter(|s| period.contains(&day.date_of(s.started_at)))
+        .cloned()
+        .collect();
+    stats.totals.sessions = period_sessions.len();
+
+    period_sessions.sort_by(|a, b| b.active_secs.cmp(&a.active_secs));
+    period_sessions.truncate(LONGEST_SESSIONS);
+    stats.longest_sessions = period_sessions;
+
+    (stats.current_streak, stats.longest_streak) = streaks(&reading_days, &day.today());
+
+    stats
+}
+
+/// Current and longest runs of consecutive reading days
+fn streaks(reading_days: &BTreeSet<String>, today: &str) -> (u32, u32) {
+    let dates: Vec<NaiveDate> = reading_days
+        .iter()
+        .filter_map(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .collect();
+
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+
+    for date in &dates {
+        run = match previous {
+            Some(prev) if *date - prev == TimeDelta::days(1) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(*date);
+    }
+
+    let current = match (previous, NaiveDate::parse_from_str(today, "%Y-%m-%d")) {
+        // Today isn't over yet, so a streak ending yesterday is still running
+        (Some(last), Ok(today)) if today - last <= TimeDelta::days(1) => run,
+        _ => 0,
+    };
+
+    (current, longest)
+}
@@ -0,0 +1,127 @@
+use crate::{
+    prelude::*,
+    services::stores::{
+        file::{STORE_FILE, StoreFile},
+        games::Game,
+    },
+    util,
+};
+use anyhow::Context;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+/// What changed a game's totals outside of regular tracking
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditKind {
+    /// Added or taken back by hand with `adjust_playtime`
+    Adjustment,
+    Reset,
+    /// Changed by a store schema migration
+    Migration,
+}
+
+/// A recorded change to a game's `playtime` and/or `chars_read`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuditEntry {
+    pub game_id: String,
+    /// Unix timestamp (seconds) of the change
+    pub at: u64,
+    pub kind: AuditKind,
+    /// Play time in seconds before the change
+    pub playtime_before: u64,
+    pub playtime_after: u64,
+    pub chars_read_before: u64,
+    pub chars_read_after: u64,
+    pub reason: Option<String>,
+}
+
+impl AuditEntry {
+    /// Describes the change between two versions of a game
+    pub fn between(
+        game_id: &str,
+        kind: AuditKind,
+        before: &Game,
+        after: &Game,
+        reason: Option<String>,
+    ) -> Self {
+        Self {
+            game_id: game_id.to_string(),
+            at: util::unix_now(),
+            kind,
+            playtime_before: before.playtime,
+            playtime_after: after.playtime,
+            chars_read_before: before.chars_read,
+            chars_read_after: after.chars_read,
+            reason,
+        }
+    }
+}
+
+/// Audit entries ordered by time
+pub type AuditLog = Vec<AuditEntry>;
+
+pub struct AuditStore {
+    store: StoreFile,
+}
+
+impl AuditStore {
+    /// Creates store or uses existing one
+    pub fn new(app_handle: &AppHandle) -> Result<Self> {
+        debug!("Creating AuditStore");
+        let store = StoreFile::open(app_handle, STORE_FILE)?;
+
+        Ok(Self { store })
+    }
+
+    fn get_store_value(&self) -> serde_json::Value {
+        debug!("Getting audit log from store");
+        self.store
+            .get("auditLog")
+            .unwrap_or_else(|| serde_json::json!([]))
+    }
+
+    fn set_all(&self, entries: &AuditLog) -> Result<()> {
+        self.store.set("auditLog", serde_json::to_value(entries)?);
+        self.store.save().context("Failed to save store")
+    }
+
+    /// Gets the whole audit log
+    pub fn get_all(&self) -> Result<AuditLog> {
+        debug!("Getting audit log");
+        Ok(serde_json::from_value(self.get_store_value())?)
+    }
+
+    /// Gets the audit log of a single game
+    pub fn get_by_game(&self, game_id: &str) -> Result<AuditLog> {
+        debug!("Getting audit log for game: {}", game_id);
+        Ok(self
+            .get_all()?
+            .into_iter()
+            .filter(|e| e.game_id == game_id)
+            .collect())
+    }
+
+    /// Appends entries to the log
+    pub fn record(&self, entries: impl IntoIterator<Item = AuditEntry>) -> Result<()> {
+        let mut log = self.get_all()?;
+        let len = log.len();
+        log.extend(entries);
+
+        if log.len() == len {
+            return Ok(());
+        }
+
+        info!("Recording {} audit entries", log.len() - len);
+        self.set_all(&log)
+    }
+
+    /// Deletes the audit log of a game
+    pub fn delete_by_game(&self, game_id: &str) -> Result<()> {
+        info!("Deleting audit log for game: {}", game_id);
+        let mut log = self.get_all()?;
+        log.retain(|e| e.game_id != game_id);
+        self.set_all(&log)
+    }
+}
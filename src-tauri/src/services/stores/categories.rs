@@ -1,59 +1,96 @@
-use crate::prelude::*;
+use crate::{prelude::*, services::stores::database::Database};
 use log::{debug, info};
-use tauri::AppHandle;
-use tauri_plugin_store::StoreExt;
+use rusqlite::Transaction;
+use tauri::{AppHandle, Manager};
 
 pub type Categories = Vec<String>;
 
+/// Which list of categories a row of the `categories` table belongs to
+#[derive(Clone, Copy)]
+pub enum CategoryList {
+    All,
+    Selected,
+}
+
+impl CategoryList {
+    fn key(self) -> &'static str {
+        match self {
+            Self::All => "all",
+            Self::Selected => "selected",
+        }
+    }
+}
+
 pub struct CategoriesStore {
-    store: Store,
+    app_handle: AppHandle,
 }
 
 impl CategoriesStore {
     /// Creates store or uses existing one
     pub fn new(app_handle: &AppHandle) -> Result<Self> {
         debug!("Creating CategoriesStore");
-        let store = app_handle.store("store.json")?;
+        Ok(Self {
+            app_handle: app_handle.clone(),
+        })
+    }
+
+    fn get_list(&self, list: CategoryList) -> Result<Categories> {
+        self.app_handle.state::<Database>().with(|conn| {
+            let mut stmt =
+                conn.prepare("SELECT name FROM categories WHERE list = ?1 ORDER BY position")?;
+            let names = stmt
+                .query_map([list.key()], |row| row.get(0))?
+                .collect::<rusqlite::Result<Categories>>()?;
+            Ok(names)
+        })
+    }
 
-        Ok(Self { store })
+    fn set_list(&self, list: CategoryList, categories: &Categories) -> Result<()> {
+        self.app_handle.state::<Database>().with(|conn| {
+            let tx = conn.transaction()?;
+            Self::write(&tx, list, categories)?;
+            tx.commit()?;
+            Ok(())
+        })
     }
 
-    fn get_store(&self) -> serde_json::Value {
-        debug!("Getting categories from store");
-        self.store
-            .get("categories")
-            .unwrap_or_else(|| serde_json::json!([]))
+    /// Replaces a list of categories inside a transaction
+    pub(super) fn write(
+        tx: &Transaction,
+        list: CategoryList,
+        categories: &Categories,
+    ) -> Result<()> {
+        tx.execute("DELETE FROM categories WHERE list = ?1", [list.key()])?;
+
+        let mut stmt =
+            tx.prepare("INSERT INTO categories (list, position, name) VALUES (?1, ?2, ?3)")?;
+        for (position, name) in categories.iter().enumerate() {
+            stmt.execute((list.key(), position, name))?;
+        }
+        Ok(())
     }
 
     /// Gets all categories
     pub fn get_all(&self) -> Result<Categories> {
         debug!("Getting all categories");
-        Ok(serde_json::from_value(self.get_store())?)
+        self.get_list(CategoryList::All)
     }
 
     /// Sets categories array to the provided value
     pub fn set(&self, categories: Categories) -> Result<()> {
         info!("Setting categories to: {:?}", categories);
-        self.store.set("categories", categories);
-
-        Ok(())
+        self.set_list(CategoryList::All, &categories)
     }
 
     /// Gets all selected categories
     pub fn get_selected(&self) -> Result<Categories> {
         debug!("Getting all selected categories");
-        Ok(serde_json::from_value(
-            self.store
-                .get("selected_categories")
-                .unwrap_or_else(|| serde_json::json!([])),
-        )?)
+        self.get_list(CategoryList::Selected)
     }
 
     /// Sets selected categories array to the provided value
     pub fn set_selected(&self, categories: Categories) -> Result<()> {
         info!("Setting selected categories to: {:?}", categories);
-        self.store.set("selected_categories", categories);
-
-        Ok(())
+        self.set_list(CategoryList::Selected, &categories)
     }
 }
@@ -0,0 +1,253 @@
+use crate::prelude::Result;
+use crate::services::stores::{
+    categories::{Categories, CategoriesStore, CategoryList},
+    games::{Games, GamesStore},
+    sessions::{Sessions, SessionsStore},
+};
+use crate::util;
+use anyhow::Context;
+use log::{debug, info};
+use rusqlite::{Connection, DatabaseName, OptionalExtension};
+use std::{path::Path, sync::Mutex};
+use tauri::{AppHandle, Manager};
+
+/// File name of the database in the app data directory, next to `store.json`
+const DATABASE_FILE: &str = "tadoku.db";
+/// Version of the table layout, kept in SQLite's `user_version`
+const SCHEMA_VERSION: u32 = 2;
+/// `meta` key set once the library was moved over from `store.json`
+const IMPORTED_KEY: &str = "store_json_imported_at";
+/// Every table, parents before the tables referencing them
+const TABLES: [&str; 5] = ["games", "daily_stats", "sessions", "categories", "meta"];
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS games (
+    id TEXT PRIMARY KEY,
+    data TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS daily_stats (
+    game_id TEXT NOT NULL REFERENCES games(id) ON DELETE CASCADE,
+    date TEXT NOT NULL,
+    playtime INTEGER NOT NULL DEFAULT 0,
+    chars_read INTEGER NOT NULL DEFAULT 0,
+    reading_secs INTEGER NOT NULL DEFAULT 0,
+    untimed_chars INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (game_id, date)
+);
+CREATE INDEX IF NOT EXISTS daily_stats_date ON daily_stats(date);
+CREATE TABLE IF NOT EXISTS sessions (
+    id TEXT PRIMARY KEY,
+    game_id TEXT NOT NULL,
+    started_at INTEGER NOT NULL,
+    ended_at INTEGER,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS sessions_game ON sessions(game_id, started_at);
+CREATE INDEX IF NOT EXISTS sessions_started ON sessions(started_at);
+CREATE TABLE IF NOT EXISTS categories (
+    list TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    PRIMARY KEY (list, position)
+);
+CREATE TABLE IF NOT EXISTS meta (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+";
+
+/// SQLite database holding the library, categories and play history
+pub struct Database {
+    conn: Mutex<Connection>,
+}
+
+impl Database {
+    /// Opens the database in the app data directory, creating its tables when needed
+    pub fn open(app_handle: &AppHandle) -> Result<Self> {
+        let dir = app_handle.path().app_data_dir()?;
+        std::fs::create_dir_all(&dir).context("Failed to create app data directory")?;
+        let path = dir.join(DATABASE_FILE);
+
+        info!("Opening database at {:?}", path);
+        let conn = Connection::open(&path).context(format!("Failed to open {:?}", path))?;
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+
+        let version: u32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version == 0 {
+            debug!("Creating database tables, version {}", SCHEMA_VERSION);
+            conn.execute_batch(SCHEMA)
+                .context("Failed to create database tables")?;
+            conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        } else if version < SCHEMA_VERSION {
+            Self::upgrade(&conn, "main", version).context("Failed to upgrade database tables")?;
+        }
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Brings the tables of `schema` from `version` up to `SCHEMA_VERSION`
+    fn upgrade(conn: &Connection, schema: &str, version: u32) -> Result<()> {
+        debug!(
+            "Upgrading {} database tables from version {} to {}",
+            schema, version, SCHEMA_VERSION
+        );
+        if version < 2 {
+            conn.execute_batch(&format!(
+                "ALTER TABLE {}.daily_stats ADD COLUMN untimed_chars INTEGER NOT NULL DEFAULT 0",
+                schema
+            ))?;
+        }
+        conn.pragma_update(
+            Some(DatabaseName::Attached(schema)),
+            "user_version",
+            SCHEMA_VERSION,
+        )?;
+        Ok(())
+    }
+
+    /// Runs `f` with exclusive access to the connection.
+    /// `f` must not use any store, they lock the same connection.
+    pub fn with<T>(&self, f: impl FnOnce(&mut Connection) -> Result<T>) -> Result<T> {
+        let mut conn = self
+            .conn
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Database mutex poisoned"))?;
+        f(&mut conn)
+    }
+
+    /// Writes an imported library in a single transaction,
+    /// removing every game and session first when `replace` is set
+    pub fn write_library(
+        &self,
+        games: &Games,
+        sessions: &Sessions,
+        categories: &Categories,
+        selected_categories: &Categories,
+        replace: bool,
+    ) -> Result<()> {
+        self.with(|conn| {
+            let tx = conn.transaction()?;
+            if replace {
+                tx.execute("DELETE FROM games", [])?;
+                tx.execute("DELETE FROM sessions", [])?;
+            }
+
+            for (game_id, game) in games {
+                GamesStore::write(&tx, game_id, game)?;
+            }
+            for session in sessions {
+                SessionsStore::write(&tx, session)?;
+            }
+            CategoriesStore::write(&tx, CategoryList::All, categories)?;
+            CategoriesStore::write(&tx, CategoryList::Selected, selected_categories)?;
+
+            tx.commit()?;
+            Ok(())
+        })
+    }
+
+    /// Writes a consistent copy of the database to `path`, which must not exist yet
+    pub fn snapshot(&self, path: &Path) -> Result<()> {
+        self.with(|conn| {
+            conn.execute("VACUUM INTO ?1", [path.to_string_lossy().into_owned()])
+                .context(format!("Failed to copy database to {:?}", path))?;
+            Ok(())
+        })
+    }
+
+    /// Replaces every row with the rows of the database copy at `path` in a single transaction
+    pub fn restore_from(&self, path: &Path) -> Result<()> {
+        self.with(|conn| {
+            conn.execute(
+                "ATTACH DATABASE ?1 AS backup",
+                [path.to_string_lossy().into_owned()],
+            )
+            .context(format!("Failed to open database copy {:?}", path))?;
+            let result = Self::copy_tables(conn);
+            conn.execute("DETACH DATABASE backup", [])?;
+            result
+        })
+    }
+
+    fn copy_tables(conn: &mut Connection) -> Result<()> {
+        let version: u32 = conn.pragma_query_value(
+            Some(DatabaseName::Attached("backup")),
+            "user_version",
+            |row| row.get(0),
+        )?;
+        anyhow::ensure!(
+            (1..=SCHEMA_VERSION).contains(&version),
+            "Database copy has version {}, expected at most {}",
+            version,
+            SCHEMA_VERSION
+        );
+        // The copy is a temporary file, it's upgraded in place
+        if version < SCHEMA_VERSION {
+            Self::upgrade(conn, "backup", version)?;
+        }
+
+        let tx = conn.transaction()?;
+        for table in TABLES.iter().rev() {
+            tx.execute(&format!("DELETE FROM main.{}", table), [])?;
+        }
+        for table in TABLES {
+            tx.execute(
+                &format!("INSERT INTO main.{0} SELECT * FROM backup.{0}", table),
+                [],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Copies the library, categories and sessions formerly kept in `store.json` in a single
+    /// transaction. Does nothing and returns false when they were imported already.
+    pub fn import(
+        &self,
+        games: &Games,
+        sessions: &Sessions,
+        categories: &Categories,
+        selected_categories: &Categories,
+    ) -> Result<bool> {
+        self.with(|conn| {
+            let tx = conn.transaction()?;
+
+            let imported: Option<String> = tx
+                .query_row(
+                    "SELECT value FROM meta WHERE key = ?1",
+                    [IMPORTED_KEY],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            if imported.is_some() {
+                info!("store.json was already imported, skipping");
+                return Ok(false);
+            }
+
+            for (game_id, game) in games {
+                GamesStore::write(&tx, game_id, game)?;
+            }
+            for session in sessions {
+                SessionsStore::write(&tx, session)?;
+            }
+            CategoriesStore::write(&tx, CategoryList::All, categories)?;
+            CategoriesStore::write(&tx, CategoryList::Selected, selected_categories)?;
+
+            tx.execute(
+                "INSERT INTO meta (key, value) VALUES (?1, ?2)",
+                (IMPORTED_KEY, util::unix_now().to_string()),
+            )?;
+            tx.commit()?;
+
+            info!(
+                "Imported {} games and {} sessions into the database",
+                games.len(),
+                sessions.len()
+            );
+            Ok(true)
+        })
+    }
+}
@@ -0,0 +1,214 @@
+use crate::prelude::Result;
+use anyhow::Context;
+use log::{error, info, warn};
+use serde::Serialize;
+use std::{
+    fs::{self, File},
+    io::Write,
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tauri::{AppHandle, Manager, Wry};
+use tauri_plugin_store::StoreExt;
+
+pub const STORE_FILE: &str = "store.json";
+pub const SETTINGS_FILE: &str = "settings.json";
+/// Store files checked for corruption on startup
+pub const STORE_FILES: [&str; 2] = [STORE_FILE, SETTINGS_FILE];
+
+/// Directory in the app data directory holding the known-good copies
+const KNOWN_GOOD_DIR: &str = "known-good";
+/// Number of known-good copies kept of each store file, one is taken per launch
+const KNOWN_GOOD_COPIES: usize = 3;
+
+/// A tauri-plugin-store file whose writes replace it whole,
+/// so a crash mid-save can't leave it truncated
+pub struct StoreFile {
+    store: Arc<tauri_plugin_store::Store<Wry>>,
+    path: PathBuf,
+}
+
+impl Deref for StoreFile {
+    type Target = tauri_plugin_store::Store<Wry>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.store
+    }
+}
+
+impl StoreFile {
+    /// Opens a store file with the plugin's auto-save disabled, it's written by `save` only
+    pub fn open(app_handle: &AppHandle, file: &str) -> Result<Self> {
+        let store = app_handle.store_builder(file).disable_auto_save().build()?;
+        let path = tauri_plugin_store::resolve_store_path(app_handle, file)?;
+
+        Ok(Self { store, path })
+    }
+
+    /// Writes the store to a temporary file and renames it over the store file
+    pub fn save(&self) -> Result<()> {
+        let entries: serde_json::Map<String, serde_json::Value> =
+            self.store.entries().into_iter().collect();
+        write_atomic(&self.path, &serde_json::to_vec_pretty(&entries)?)
+    }
+
+    /// Replaces a store file with `bytes` and reloads the open store from it
+    pub fn replace(app_handle: &AppHandle, file: &str, bytes: &[u8]) -> Result<()> {
+        let store = Self::open(app_handle, file)?;
+        write_atomic(&store.path, bytes)?;
+        store
+            .reload_ignore_defaults()
+            .context(format!("Failed to reload {}", file))?;
+        Ok(())
+    }
+
+    /// Unloads the store files from the plugin, which would otherwise rewrite them in place
+    /// on exit. Every change was saved already.
+    pub fn close_all(app_handle: &AppHandle) {
+        for file in STORE_FILES {
+            if let Some(store) = app_handle.get_store(file) {
+                store.close_resource();
+            }
+        }
+    }
+}
+
+/// Writes a file through a temporary file renamed over it,
+/// both synced to disk so a power loss leaves the old or the new contents
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let tmp_path = path.with_extension("json.tmp");
+    File::create(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(bytes)?;
+            file.sync_all()
+        })
+        .context(format!("Failed to write {:?}", tmp_path))?;
+    fs::rename(&tmp_path, path).context(format!("Failed to replace {:?}", path))?;
+
+    // The rename is only durable once the directory entry is, Windows has no way to sync it
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        File::open(dir)
+            .and_then(|dir| dir.sync_all())
+            .context(format!("Failed to sync {:?}", dir))?;
+    }
+    Ok(())
+}
+
+/// The bytes are a JSON object, as every store file is
+pub fn is_valid(bytes: &[u8]) -> bool {
+    serde_json::from_slice::<serde_json::Map<String, serde_json::Value>>(bytes).is_ok()
+}
+
+/// A store file that couldn't be parsed on startup and was started over empty
+#[derive(Serialize, Debug, Clone)]
+pub struct CorruptStore {
+    pub file: String,
+    /// Where the unreadable file was moved to
+    pub moved_to: String,
+    /// A known-good copy is available to restore
+    pub restorable: bool,
+}
+
+/// Keeps store files readable: checks them on startup, keeps known-good copies
+/// and restores the newest one on request
+pub struct StoreRecovery<'a> {
+    app_handle: &'a AppHandle,
+}
+
+impl<'a> StoreRecovery<'a> {
+    pub fn new(app_handle: &'a AppHandle) -> Self {
+        Self { app_handle }
+    }
+
+    fn store_path(&self, file: &str) -> Result<PathBuf> {
+        Ok(tauri_plugin_store::resolve_store_path(
+            self.app_handle,
+            file,
+        )?)
+    }
+
+    /// Path of the known-good copy of `file`, 1 being the newest
+    fn copy_path(&self, file: &str, index: usize) -> Result<PathBuf> {
+        Ok(self
+            .app_handle
+            .path()
+            .app_data_dir()?
+            .join(KNOWN_GOOD_DIR)
+            .join(format!("{}.{}", file, index)))
+    }
+
+    /// Checks every store file before the stores are opened. A valid file becomes the newest
+    /// known-good copy, an unreadable one is moved aside so the app starts without it.
+    pub fn check(&self) -> Result<Vec<CorruptStore>> {
+        let mut corrupt = Vec::new();
+
+        for file in STORE_FILES {
+            let path = self.store_path(file)?;
+            let bytes = match fs::read(&path) {
+                Ok(bytes) => bytes,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e).context(format!("Failed to read {:?}", path)),
+            };
+
+            if is_valid(&bytes) {
+                if let Err(e) = self.keep_known_good(file, &bytes) {
+                    warn!("Failed to keep a known-good copy of {}: {:?}", file, e);
+                }
+                continue;
+            }
+
+            let moved_to = path.with_extension("json.corrupt");
+            error!("{} is unreadable, moving it to {:?}", file, moved_to);
+            fs::rename(&path, &moved_to).context(format!("Failed to move {:?} aside", path))?;
+
+            corrupt.push(CorruptStore {
+                file: file.to_string(),
+                moved_to: moved_to.to_string_lossy().into_owned(),
+                restorable: self.newest_known_good(file)?.is_some(),
+            });
+        }
+
+        Ok(corrupt)
+    }
+
+    fn keep_known_good(&self, file: &str, bytes: &[u8]) -> Result<()> {
+        for index in (1..KNOWN_GOOD_COPIES).rev() {
+            let from = self.copy_path(file, index)?;
+            if from.exists() {
+                fs::rename(&from, self.copy_path(file, index + 1)?)?;
+            }
+        }
+        write_atomic(&self.copy_path(file, 1)?, bytes)
+    }
+
+    /// Contents of the newest known-good copy that still parses
+    fn newest_known_good(&self, file: &str) -> Result<Option<Vec<u8>>> {
+        for index in 1..=KNOWN_GOOD_COPIES {
+            let Ok(bytes) = fs::read(self.copy_path(file, index)?) else {
+                continue;
+            };
+            if is_valid(&bytes) {
+                return Ok(Some(bytes));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Replaces a store file with its newest known-good copy and reloads the open store
+    pub fn restore(&self, file: &str) -> Result<()> {
+        anyhow::ensure!(STORE_FILES.contains(&file), "Unknown store file: {}", file);
+
+        let bytes = self
+            .newest_known_good(file)?
+            .context(format!("No known-good copy of {} to restore", file))?;
+
+        info!("Restoring {} from its newest known-good copy", file);
+        StoreFile::replace(self.app_handle, file, &bytes)
+    }
+}
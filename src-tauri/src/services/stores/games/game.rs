@@ -1,9 +1,12 @@
 use super::super::categories::Categories;
 use super::character::Character;
+use super::process_matcher::ProcessMatcher;
+use super::progress::Progress;
 use crate::prelude::Fetchable;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct Game {
     pub title: String,
     #[serde(default)]
@@ -13,6 +16,9 @@ pub struct Game {
     pub image_url: String,
     pub exe_file_path: String,
     pub process_file_path: String,
+    /// How the game's running process is recognized
+    #[serde(default)]
+    pub process_matcher: ProcessMatcher,
     /// Play time in seconds
     #[serde(default)]
     pub playtime: u64,
@@ -21,6 +27,20 @@ pub struct Game {
     pub last_played: Option<u64>,
     pub first_played: Option<u64>,
     pub last_play_date: Option<String>,
+    /// Seconds played per day, keyed by `YYYY-MM-DD`
+    #[serde(default)]
+    pub daily_playtime: BTreeMap<String, u64>,
+    /// Characters read per day, keyed by `YYYY-MM-DD`
+    #[serde(default)]
+    pub daily_chars: BTreeMap<String, u64>,
+    /// Seconds of reading with a known character count per day, keyed by `YYYY-MM-DD`.
+    /// Only this time counts towards reading speed.
+    #[serde(default)]
+    pub daily_reading_secs: BTreeMap<String, u64>,
+    /// Part of `daily_chars` added with no reading time behind it, by adjustments and manual
+    /// sessions without a duration. Left out of reading speed.
+    #[serde(default)]
+    pub daily_untimed_chars: BTreeMap<String, u64>,
     #[serde(default)]
     pub is_pinned: bool,
     pub is_nsfw: bool,
@@ -31,10 +51,106 @@ pub struct Game {
     pub categories: Categories,
     // TODO: Make its own struct?
     pub characters: Option<Vec<Character>>,
-    /// Cumulative characters read (from exSTATic)
+    /// Cumulative characters read, from exSTATic and from manual sessions
     #[serde(default)]
     pub chars_read: u64,
+    /// Last cumulative count reported by exSTATic, only its increases are added to `chars_read`.
+    /// `None` until the first report or manual change, `chars_read` stands in for it then.
+    #[serde(default)]
+    pub exstatic_chars: Option<u64>,
     /// Total character count from Jiten API (pre-fetched at startup)
     #[serde(default)]
     pub jiten_char_count: Fetchable<u64>,
+    /// Reading progress, only filled in when loading games and never stored
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub progress: Option<Progress>,
+}
+
+impl Game {
+    /// Credits playtime to a reading day (`YYYY-MM-DD`), which may be in the past
+    pub fn add_playtime_on(&mut self, playtime: u64, date: &str) {
+        self.playtime += playtime;
+        *self.daily_playtime.entry(date.to_string()).or_default() += playtime;
+
+        match self.last_play_date.as_deref() {
+            Some(last) if last == date => self.today_playtime += playtime,
+            // Playtime from an earlier day only goes to the ledger
+            Some(last) if last > date => {}
+            _ => {
+                self.today_playtime = playtime;
+                self.last_play_date = Some(date.to_string());
+            }
+        }
+    }
+
+    /// Takes back playtime previously credited to a reading day
+    pub fn remove_playtime_on(&mut self, playtime: u64, date: &str) {
+        self.playtime = self.playtime.saturating_sub(playtime);
+        remove_from_ledger(&mut self.daily_playtime, date, playtime);
+
+        if self.last_play_date.as_deref() == Some(date) {
+            self.today_playtime = self.today_playtime.saturating_sub(playtime);
+        }
+    }
+
+    /// Takes `chars_read` as exSTATic's last count when none was recorded yet,
+    /// so characters changed outside exSTATic aren't mistaken for its progress
+    pub fn pin_exstatic_chars(&mut self) {
+        self.exstatic_chars.get_or_insert(self.chars_read);
+    }
+
+    /// Adds characters read on a reading day to the cumulative count
+    pub fn add_chars_on(&mut self, chars: u64, date: &str) {
+        self.chars_read += chars;
+        *self.daily_chars.entry(date.to_string()).or_default() += chars;
+    }
+
+    /// Takes back characters previously added to a reading day
+    pub fn remove_chars_on(&mut self, chars: u64, date: &str) {
+        self.chars_read = self.chars_read.saturating_sub(chars);
+        remove_from_ledger(&mut self.daily_chars, date, chars);
+    }
+
+    /// Adds characters read on a reading day with no reading time to go with them
+    pub fn add_untimed_chars_on(&mut self, chars: u64, date: &str) {
+        self.add_chars_on(chars, date);
+        *self
+            .daily_untimed_chars
+            .entry(date.to_string())
+            .or_default() += chars;
+    }
+
+    /// Takes back characters from a reading day, untimed ones first
+    pub fn remove_untimed_chars_on(&mut self, chars: u64, date: &str) {
+        self.remove_chars_on(chars, date);
+        remove_from_ledger(&mut self.daily_untimed_chars, date, chars);
+    }
+
+    /// Characters read on a reading day along with reading time, the ones reading speed is
+    /// based on
+    pub fn timed_chars_on(&self, date: &str) -> u64 {
+        let chars = self.daily_chars.get(date).copied().unwrap_or(0);
+        let untimed = self.daily_untimed_chars.get(date).copied().unwrap_or(0);
+        chars.saturating_sub(untimed)
+    }
+
+    /// Records seconds spent reading with a known character count on a reading day
+    pub fn add_reading_on(&mut self, secs: u64, date: &str) {
+        *self.daily_reading_secs.entry(date.to_string()).or_default() += secs;
+    }
+
+    /// Takes back reading seconds previously added to a reading day
+    pub fn remove_reading_on(&mut self, secs: u64, date: &str) {
+        remove_from_ledger(&mut self.daily_reading_secs, date, secs);
+    }
+}
+
+/// Subtracts from a daily ledger entry, dropping it once empty
+fn remove_from_ledger(ledger: &mut BTreeMap<String, u64>, date: &str, amount: u64) {
+    if let Some(value) = ledger.get_mut(date) {
+        *value = value.saturating_sub(amount);
+        if *value == 0 {
+            ledger.remove(date);
+        }
+    }
 }
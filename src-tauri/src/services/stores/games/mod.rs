@@ -1,20 +1,52 @@
 mod character;
 mod game;
-use crate::prelude::*;
-use crate::util::image;
+mod process_matcher;
+mod progress;
+mod repository;
+use crate::util::{DayBoundary, image};
 use anyhow::{Context, Result};
 pub use character::Character;
-use chrono::Local;
 pub use game::Game;
 use log::{debug, info};
-use std::{collections::HashMap, fs, path::PathBuf, time};
-use tauri::{AppHandle, Manager};
-use tauri_plugin_store::StoreExt;
+pub use process_matcher::ProcessMatcher;
+pub use progress::Progress;
+pub use repository::GamesRepository;
+use rusqlite::{Connection, Transaction};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::PathBuf,
+    time,
+};
+use tauri::{AppHandle, Manager, State};
 
 pub type Games = HashMap<String, Game>;
 
+/// Library-wide totals for a single day
+#[derive(Serialize, Debug, Default, Clone, Copy)]
+pub struct DailyTotal {
+    /// Play time in seconds
+    pub playtime: u64,
+    pub chars_read: u64,
+}
+
+/// Daily totals keyed by `YYYY-MM-DD`
+pub type DailyTotals = BTreeMap<String, DailyTotal>;
+
+/// Fields of `Game` kept in the `daily_stats` table instead of the game's JSON
+const LEDGER_KEYS: [&str; 4] = [
+    "daily_playtime",
+    "daily_chars",
+    "daily_reading_secs",
+    "daily_untimed_chars",
+];
+
+/// Playtime, chars read, reading seconds and untimed chars of a game on one day
+type LedgerDay = (u64, u64, u64, u64);
+
 pub struct GamesStore {
-    store: Store,
+    app_handle: AppHandle,
     base_app_path: PathBuf,
 }
 
@@ -22,26 +54,170 @@ impl GamesStore {
     /// Creates store or uses existing one
     pub fn new(app_handle: &AppHandle) -> Result<Self> {
         debug!("Creating GamesStore");
-        let store = app_handle.store("store.json")?;
         let base_app_path = app_handle.path().app_local_data_dir()?;
 
         Ok(Self {
-            store,
+            app_handle: app_handle.clone(),
             base_app_path,
         })
     }
 
-    fn get_store_value(&self) -> serde_json::Value {
-        debug!("Getting games from store");
-        self.store
-            .get("gamesData")
-            .unwrap_or_else(|| serde_json::json!({}))
+    fn repository(&self) -> State<'_, GamesRepository> {
+        self.app_handle.state::<GamesRepository>()
+    }
+
+    /// Reads games as stored, every game or only `game_id`
+    fn read(conn: &Connection, game_id: Option<&str>) -> Result<Games> {
+        let mut games = Games::new();
+
+        let mut stmt = conn.prepare("SELECT id, data FROM games WHERE ?1 IS NULL OR id = ?1")?;
+        let rows = stmt.query_map([game_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for row in rows {
+            let (id, data) = row?;
+            let game: Game =
+                serde_json::from_str(&data).context(format!("Failed to parse game {}", id))?;
+            games.insert(id, game);
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT game_id, date, playtime, chars_read, reading_secs, untimed_chars \
+             FROM daily_stats WHERE ?1 IS NULL OR game_id = ?1",
+        )?;
+        let rows = stmt.query_map([game_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, u64>(2)?,
+                row.get::<_, u64>(3)?,
+                row.get::<_, u64>(4)?,
+                row.get::<_, u64>(5)?,
+            ))
+        })?;
+        for row in rows {
+            let (id, date, playtime, chars, reading_secs, untimed_chars) = row?;
+            let Some(game) = games.get_mut(&id) else {
+                continue;
+            };
+            if playtime > 0 {
+                game.daily_playtime.insert(date.clone(), playtime);
+            }
+            if chars > 0 {
+                game.daily_chars.insert(date.clone(), chars);
+            }
+            if reading_secs > 0 {
+                game.daily_reading_secs.insert(date.clone(), reading_secs);
+            }
+            if untimed_chars > 0 {
+                game.daily_untimed_chars.insert(date, untimed_chars);
+            }
+        }
+
+        Ok(games)
+    }
+
+    /// Inserts or replaces a game inside a transaction
+    pub(super) fn write(tx: &Transaction, game_id: &str, game: &Game) -> Result<()> {
+        tx.execute(
+            "INSERT INTO games (id, data) VALUES (?1, ?2) \
+             ON CONFLICT (id) DO UPDATE SET data = excluded.data",
+            (game_id, Self::encode(game)?),
+        )?;
+        let stored = Self::stored_ledger(tx, game_id)?;
+        Self::write_ledger(tx, game_id, &stored, &Self::ledger(game))
+    }
+
+    /// Removes a game and its daily stats inside a transaction
+    fn remove(tx: &Transaction, game_id: &str) -> Result<()> {
+        tx.execute("DELETE FROM games WHERE id = ?1", [game_id])?;
+        Ok(())
+    }
+
+    /// The game's JSON without its daily ledgers
+    fn encode(game: &Game) -> Result<String> {
+        let mut value = serde_json::to_value(game)?;
+        if let Some(fields) = value.as_object_mut() {
+            for key in LEDGER_KEYS {
+                fields.remove(key);
+            }
+        }
+        Ok(value.to_string())
+    }
+
+    fn ledger(game: &Game) -> BTreeMap<String, LedgerDay> {
+        let mut ledger: BTreeMap<String, LedgerDay> = BTreeMap::new();
+
+        for (date, playtime) in &game.daily_playtime {
+            ledger.entry(date.clone()).or_default().0 = *playtime;
+        }
+        for (date, chars) in &game.daily_chars {
+            ledger.entry(date.clone()).or_default().1 = *chars;
+        }
+        for (date, reading_secs) in &game.daily_reading_secs {
+            ledger.entry(date.clone()).or_default().2 = *reading_secs;
+        }
+        for (date, untimed_chars) in &game.daily_untimed_chars {
+            ledger.entry(date.clone()).or_default().3 = *untimed_chars;
+        }
+
+        ledger
+    }
+
+    fn stored_ledger(tx: &Transaction, game_id: &str) -> Result<BTreeMap<String, LedgerDay>> {
+        let mut stmt = tx.prepare_cached(
+            "SELECT date, playtime, chars_read, reading_secs, untimed_chars FROM daily_stats \
+             WHERE game_id = ?1",
+        )?;
+        let rows = stmt.query_map([game_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                (
+                    row.get::<_, u64>(1)?,
+                    row.get::<_, u64>(2)?,
+                    row.get::<_, u64>(3)?,
+                    row.get::<_, u64>(4)?,
+                ),
+            ))
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<BTreeMap<_, _>>>()?)
+    }
+
+    /// Writes only the days that changed between two versions of a game's ledger
+    fn write_ledger(
+        tx: &Transaction,
+        game_id: &str,
+        before: &BTreeMap<String, LedgerDay>,
+        after: &BTreeMap<String, LedgerDay>,
+    ) -> Result<()> {
+        let mut upsert = tx.prepare_cached(
+            "INSERT INTO daily_stats \
+             (game_id, date, playtime, chars_read, reading_secs, untimed_chars) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6) \
+             ON CONFLICT (game_id, date) DO UPDATE SET playtime = excluded.playtime, \
+             chars_read = excluded.chars_read, reading_secs = excluded.reading_secs, \
+             untimed_chars = excluded.untimed_chars",
+        )?;
+        for (date, day) in after {
+            if before.get(date) != Some(day) {
+                upsert.execute((game_id, date, day.0, day.1, day.2, day.3))?;
+            }
+        }
+
+        let mut delete =
+            tx.prepare_cached("DELETE FROM daily_stats WHERE game_id = ?1 AND date = ?2")?;
+        for date in before.keys().filter(|date| !after.contains_key(*date)) {
+            delete.execute((game_id, date))?;
+        }
+
+        Ok(())
     }
 
     /// Gets all games in store (image_urls are Paths)
     pub fn get_all(&self) -> Result<Games> {
         debug!("Getting all games");
-        let mut games: Games = serde_json::from_value(self.get_store_value())?;
+        let mut games = self.repository().get_all()?;
 
         for game in games.values_mut() {
             if game.image_url.is_empty() {
@@ -59,9 +235,8 @@ impl GamesStore {
     /// Deletes a game from the store (also removes images from filesystem)
     pub fn delete(&self, game_id: &str) -> Result<()> {
         info!("Deleting game with id: {}", game_id);
-        let mut games: Games = serde_json::from_value(self.get_store_value())?;
 
-        if let Some(removed_game) = games.remove(game_id) {
+        if let Some(removed_game) = self.repository().remove(game_id)? {
             if !removed_game.image_url.is_empty() {
                 let image_path =
                     image::construct_image_path(&self.base_app_path, &removed_game.image_url)?;
@@ -80,15 +255,13 @@ impl GamesStore {
                     }
                 }
             }
-
-            self.store.set("gamesData", serde_json::to_value(games)?);
-            self.store.save()?;
         }
 
         Ok(())
     }
 
-    /// Resets a game's stats (playtime, today_playtime, last_played, first_played and last_play_date)
+    /// Resets a game's stats (playtime, today_playtime, last_played, first_played, last_play_date,
+    /// chars_read and the daily ledgers)
     pub fn reset_stats(&self, game_id: &str) -> Result<()> {
         info!("Resetting stats for game with id: {}", game_id);
         self.update_game(game_id, |game| {
@@ -97,62 +270,86 @@ impl GamesStore {
             game.last_played = None;
             game.first_played = None;
             game.last_play_date = None;
+            // exSTATic's count so far stays uncredited, only what's read after the reset counts
+            game.pin_exstatic_chars();
             game.chars_read = 0;
+            game.daily_playtime.clear();
+            game.daily_chars.clear();
+            game.daily_reading_secs.clear();
+            game.daily_untimed_chars.clear();
         })
     }
 
     /// Saves a game to the store
     pub fn save(&self, game_id: String, game_data: Game) -> Result<()> {
         info!("Saving game with id: {}", game_id);
-        let mut games: Games = serde_json::from_value(self.get_store_value())?;
-        games.insert(game_id, game_data);
-
-        self.store.set("gamesData", serde_json::to_value(games)?);
-        self.store.save().context("Failed to save store")?;
-
-        Ok(())
+        self.repository().insert(game_id, game_data)
     }
 
     /// Generic update method for a single game.
-    /// Handles loading, updating, and saving in one transaction.
+    /// Mutations of the library are serialized, so concurrent updates can't undo each other.
     pub fn update_game<F>(&self, game_id: &str, update_fn: F) -> Result<()>
     where
         F: FnOnce(&mut Game),
     {
         debug!("Updating game with id: {}", game_id);
-        let mut games: Games = serde_json::from_value(self.get_store_value())?;
-
-        let game = games
-            .get_mut(game_id)
-            .context(format!("Game with id {} not found", game_id))?;
-
-        update_fn(game);
-        self.store.set("gamesData", serde_json::to_value(games)?);
-        self.store.save().context("Failed to save store")?;
-        Ok(())
+        self.repository().update(game_id, update_fn)
     }
 
     /// Gets a game by id
     pub fn get(&self, game_id: &str) -> Option<Game> {
         debug!("Getting game with id: {}", game_id);
-        let games_data = self.get_store_value();
-        let game_val = games_data.get(game_id)?;
-
-        serde_json::from_value::<Game>(game_val.clone()).ok()
+        self.repository().get(game_id).ok()?
     }
 
     /// Logic-heavy helpers that benefit from being in the store layer
-    pub fn update_playtime(&self, game_id: &str, playtime: u64) -> Result<()> {
+    pub fn update_playtime(&self, game_id: &str, playtime: u64, day: &DayBoundary) -> Result<()> {
+        self.update_playtime_on(game_id, playtime, &day.today())
+    }
+
+    /// Credits playtime to a specific reading day (`YYYY-MM-DD`), which may be in the past
+    pub fn update_playtime_on(&self, game_id: &str, playtime: u64, date: &str) -> Result<()> {
+        self.update_game(game_id, |game| game.add_playtime_on(playtime, date))
+    }
+
+    /// Records the cumulative chars read reported by exSTATic and credits its increase,
+    /// along with the `reading_secs` it took, to today
+    pub fn update_chars_read(
+        &self,
+        game_id: &str,
+        chars_read: u64,
+        reading_secs: u64,
+        day: &DayBoundary,
+    ) -> Result<()> {
         self.update_game(game_id, |game| {
-            game.playtime += playtime;
-            let current_date = Local::now().format("%Y-%m-%d").to_string();
-
-            if game.last_play_date.as_ref() != Some(&current_date) {
-                game.today_playtime = playtime;
-                game.last_play_date = Some(current_date);
-            } else {
-                game.today_playtime += playtime;
+            let last = game.exstatic_chars.unwrap_or(game.chars_read);
+            let delta = chars_read.saturating_sub(last);
+            game.exstatic_chars = Some(chars_read);
+
+            let today = day.today();
+            if delta > 0 {
+                game.add_chars_on(delta, &today);
             }
+            game.add_reading_on(reading_secs, &today);
+        })
+    }
+
+    /// Sums the daily ledgers of every game in the library
+    pub fn get_daily_totals(&self) -> Result<DailyTotals> {
+        debug!("Getting daily totals");
+        self.repository().read(|games| {
+            let mut totals = DailyTotals::new();
+
+            for game in games.values() {
+                for (date, playtime) in &game.daily_playtime {
+                    totals.entry(date.clone()).or_default().playtime += playtime;
+                }
+                for (date, chars) in &game.daily_chars {
+                    totals.entry(date.clone()).or_default().chars_read += chars;
+                }
+            }
+
+            totals
         })
     }
 
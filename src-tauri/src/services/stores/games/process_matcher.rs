@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+/// How a running process is recognized as a game's process
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum ProcessMatcher {
+    /// Executable path equals `process_file_path`
+    #[default]
+    ExactPath,
+    /// Executable file name equals the file name of `process_file_path`, wherever it runs from
+    FileName,
+    /// Executable path matches a glob pattern (e.g. `D:\Games\*\game*.exe`)
+    PathGlob(String),
+    /// Command line matches a regular expression
+    CmdlineRegex(String),
+    /// Any process whose executable lives under a directory,
+    /// the directory of `process_file_path` when `None`
+    InstallDir(Option<String>),
+}
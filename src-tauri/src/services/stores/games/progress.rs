@@ -0,0 +1,33 @@
+use serde::Serialize;
+
+/// How far a game has been read, from its Jiten character count
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// Share of the game read, 0-100
+    pub percent: f64,
+    pub remaining_chars: u64,
+    /// Recent reading speed the estimate is based on
+    pub chars_per_hour: Option<u64>,
+    /// Estimated hours left at `chars_per_hour`
+    pub eta_hours: Option<f64>,
+}
+
+impl Progress {
+    /// `None` when the total character count is unknown
+    pub fn new(chars_read: u64, total_chars: u64, chars_per_hour: Option<u64>) -> Option<Self> {
+        if total_chars == 0 {
+            return None;
+        }
+
+        let remaining_chars = total_chars.saturating_sub(chars_read);
+        let percent = (chars_read as f64 / total_chars as f64 * 100.0).min(100.0);
+        let eta_hours = chars_per_hour.map(|speed| remaining_chars as f64 / speed as f64);
+
+        Some(Self {
+            percent,
+            remaining_chars,
+            chars_per_hour,
+            eta_hours,
+        })
+    }
+}
@@ -0,0 +1,199 @@
+use super::{Game, Games, GamesStore};
+use crate::{prelude::Result, services::stores::database::Database};
+use anyhow::Context;
+use log::{debug, error, info};
+use std::{
+    collections::HashSet,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+use tauri::{AppHandle, Manager};
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+
+/// How long the library has to go without changes before they are written to the database
+const FLUSH_DELAY: Duration = Duration::from_secs(2);
+/// Longest changes wait while new ones keep arriving, exSTATic updates a game every few seconds
+const FLUSH_MAX_DELAY: Duration = Duration::from_secs(30);
+
+struct Library {
+    games: Games,
+    /// Ids of games changed or deleted since the last flush
+    dirty: HashSet<String>,
+}
+
+/// The authoritative copy of the library, shared through Tauri state.
+/// Every mutation goes through it one at a time and is written to the database shortly after.
+pub struct GamesRepository {
+    library: Mutex<Library>,
+    changed: Notify,
+}
+
+impl GamesRepository {
+    /// Loads the library from the database
+    pub fn load(database: &Database) -> Result<Self> {
+        let games = database
+            .with(|conn| GamesStore::read(conn, None))
+            .context("Failed to load games from database")?;
+        info!("Loaded {} games", games.len());
+
+        Ok(Self {
+            library: Mutex::new(Library {
+                games,
+                dirty: HashSet::new(),
+            }),
+            changed: Notify::new(),
+        })
+    }
+
+    /// Replaces the library with the games in the database, dropping unflushed changes
+    pub fn reload(&self, database: &Database) -> Result<()> {
+        let games = database
+            .with(|conn| GamesStore::read(conn, None))
+            .context("Failed to load games from database")?;
+        info!("Reloaded {} games", games.len());
+
+        let mut library = self.lock()?;
+        library.games = games;
+        library.dirty.clear();
+        Ok(())
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Library>> {
+        self.library
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Games repository mutex poisoned"))
+    }
+
+    fn mark_dirty(&self, library: &mut Library, game_id: &str) {
+        library.dirty.insert(game_id.to_string());
+        self.changed.notify_one();
+    }
+
+    /// Runs `f` on the library without copying it
+    pub fn read<T>(&self, f: impl FnOnce(&Games) -> T) -> Result<T> {
+        Ok(f(&self.lock()?.games))
+    }
+
+    pub fn get_all(&self) -> Result<Games> {
+        Ok(self.lock()?.games.clone())
+    }
+
+    pub fn get(&self, game_id: &str) -> Result<Option<Game>> {
+        Ok(self.lock()?.games.get(game_id).cloned())
+    }
+
+    /// Adds a game or replaces it
+    pub fn insert(&self, game_id: String, game: Game) -> Result<()> {
+        let mut library = self.lock()?;
+        self.mark_dirty(&mut library, &game_id);
+        library.games.insert(game_id, game);
+        Ok(())
+    }
+
+    /// Applies `update_fn` to a game, `update_fn` must not use the repository
+    pub fn update<F>(&self, game_id: &str, update_fn: F) -> Result<()>
+    where
+        F: FnOnce(&mut Game),
+    {
+        let mut library = self.lock()?;
+        let game = library
+            .games
+            .get_mut(game_id)
+            .context(format!("Game with id {} not found", game_id))?;
+
+        update_fn(game);
+        self.mark_dirty(&mut library, game_id);
+        Ok(())
+    }
+
+    /// Removes a game, returning it when it existed
+    pub fn remove(&self, game_id: &str) -> Result<Option<Game>> {
+        let mut library = self.lock()?;
+        let removed = library.games.remove(game_id);
+        if removed.is_some() {
+            self.mark_dirty(&mut library, game_id);
+        }
+        Ok(removed)
+    }
+
+    /// Writes the games changed since the last flush to the database in one transaction
+    pub fn flush(&self, database: &Database) -> Result<()> {
+        let pending: Vec<(String, Option<Game>)> = {
+            let mut library = self.lock()?;
+            let Library { games, dirty } = &mut *library;
+            dirty
+                .drain()
+                .map(|id| {
+                    let game = games.get(&id).cloned();
+                    (id, game)
+                })
+                .collect()
+        };
+
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        debug!("Flushing {} changed games", pending.len());
+        let result = database.with(|conn| {
+            let tx = conn.transaction()?;
+            for (game_id, game) in &pending {
+                match game {
+                    Some(game) => GamesStore::write(&tx, game_id, game)?,
+                    None => GamesStore::remove(&tx, game_id)?,
+                }
+            }
+            tx.commit()?;
+            Ok(())
+        });
+
+        if result.is_err() {
+            // Try again with the next flush, newer changes to these games are kept
+            let mut library = self.lock()?;
+            library.dirty.extend(pending.into_iter().map(|(id, _)| id));
+        }
+        result.context("Failed to write games to database")
+    }
+
+    /// Flushes changes once they settle for `FLUSH_DELAY` or have waited `FLUSH_MAX_DELAY`,
+    /// and a last time on shutdown
+    pub fn spawn(app_handle: &AppHandle, token: CancellationToken) {
+        let app_handle = app_handle.clone();
+
+        tauri::async_runtime::spawn(async move {
+            let repository = app_handle.state::<GamesRepository>();
+            let database = app_handle.state::<Database>();
+
+            loop {
+                tokio::select! {
+                    _ = token.cancelled() => break,
+                    _ = repository.changed.notified() => {}
+                }
+
+                // Every further change restarts the delay, up to the deadline
+                let deadline = Instant::now() + FLUSH_MAX_DELAY;
+                loop {
+                    let delay = FLUSH_DELAY.min(deadline.saturating_duration_since(Instant::now()));
+                    tokio::select! {
+                        _ = token.cancelled() => break,
+                        _ = repository.changed.notified() => continue,
+                        _ = tokio::time::sleep(delay) => break,
+                    }
+                }
+                if token.is_cancelled() {
+                    break;
+                }
+
+                if let Err(e) = repository.flush(&database) {
+                    error!("Failed to flush games: {:?}", e);
+                }
+            }
+
+            info!("Shutdown signal received, flushing games");
+            if let Err(e) = repository.flush(&database) {
+                error!("Failed to flush games on shutdown: {:?}", e);
+            }
+        });
+    }
+}
@@ -1,3 +1,7 @@
+pub mod audit;
 pub mod categories;
+pub mod database;
+pub mod file;
 pub mod games;
+pub mod sessions;
 pub mod settings;
@@ -0,0 +1,343 @@
+use crate::{
+    prelude::*,
+    services::stores::{database::Database, settings::PlaytimeMode},
+    util,
+};
+use anyhow::Context;
+use log::{debug, info};
+use rusqlite::{Connection, Params, Transaction};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+/// What produced the playtime recorded in a session
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackingSource {
+    Classic,
+    ExStatic,
+    /// Logged by hand for reading Tadoku couldn't track
+    Manual,
+}
+
+impl From<PlaytimeMode> for TrackingSource {
+    fn from(mode: PlaytimeMode) -> Self {
+        match mode {
+            PlaytimeMode::Classic => Self::Classic,
+            PlaytimeMode::ExStatic => Self::ExStatic,
+        }
+    }
+}
+
+/// A stretch of a session during which tracking was paused manually
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PauseInterval {
+    /// Unix timestamp (seconds)
+    pub started_at: u64,
+    /// Unix timestamp (seconds), `None` while still paused
+    pub ended_at: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlaySession {
+    pub id: String,
+    pub game_id: String,
+    /// Unix timestamp (seconds) of when tracking started
+    pub started_at: u64,
+    /// Unix timestamp (seconds) of when tracking stopped, `None` while the session is running
+    pub ended_at: Option<u64>,
+    /// Seconds credited to the game during this session
+    pub active_secs: u64,
+    /// Game's cumulative `chars_read` when the session started
+    pub chars_read_start: u64,
+    /// Game's cumulative `chars_read` when the session was last updated
+    pub chars_read_end: u64,
+    pub source: TrackingSource,
+    #[serde(default)]
+    pub pauses: Vec<PauseInterval>,
+    #[serde(default)]
+    pub note: Option<String>,
+    /// Reading speed over the session, `None` when no characters were counted
+    #[serde(default)]
+    pub chars_per_hour: Option<u64>,
+    /// Game's `first_played` before this manual session was credited, restored when it's taken back
+    #[serde(default)]
+    pub first_played_before: Option<u64>,
+    /// Game's `last_played` before this manual session was credited, restored when it's taken back
+    #[serde(default)]
+    pub last_played_before: Option<u64>,
+}
+
+impl PlaySession {
+    fn close_pause(&mut self, at: u64) {
+        if let Some(pause) = self.pauses.last_mut().filter(|p| p.ended_at.is_none()) {
+            pause.ended_at = Some(at);
+        }
+    }
+
+    /// Characters read during the session
+    pub fn chars_read(&self) -> u64 {
+        self.chars_read_end.saturating_sub(self.chars_read_start)
+    }
+
+    /// Recomputes `chars_per_hour` from the session's totals
+    pub fn refresh_speed(&mut self) {
+        self.chars_per_hour = util::chars_per_hour(self.chars_read(), self.active_secs);
+    }
+}
+
+/// Sessions ordered by start time
+pub type Sessions = Vec<PlaySession>;
+
+pub struct SessionsStore {
+    app_handle: AppHandle,
+}
+
+impl SessionsStore {
+    /// Creates store or uses existing one
+    pub fn new(app_handle: &AppHandle) -> Result<Self> {
+        debug!("Creating SessionsStore");
+        Ok(Self {
+            app_handle: app_handle.clone(),
+        })
+    }
+
+    fn database(&self) -> State<'_, Database> {
+        self.app_handle.state::<Database>()
+    }
+
+    /// Reads the sessions matching a `WHERE` clause, ordered by start time
+    fn query(conn: &Connection, condition: &str, params: impl Params) -> Result<Sessions> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, data FROM sessions WHERE {} ORDER BY started_at, rowid",
+            condition
+        ))?;
+        let rows = stmt.query_map(params, |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        let mut sessions = Sessions::new();
+        for row in rows {
+            let (id, data) = row?;
+            sessions.push(
+                serde_json::from_str(&data).context(format!("Failed to parse session {}", id))?,
+            );
+        }
+        Ok(sessions)
+    }
+
+    /// Inserts or replaces a session inside a transaction
+    pub(super) fn write(tx: &Transaction, session: &PlaySession) -> Result<()> {
+        tx.execute(
+            "INSERT INTO sessions (id, game_id, started_at, ended_at, data) \
+             VALUES (?1, ?2, ?3, ?4, ?5) \
+             ON CONFLICT (id) DO UPDATE SET game_id = excluded.game_id, \
+             started_at = excluded.started_at, ended_at = excluded.ended_at, data = excluded.data",
+            (
+                &session.id,
+                &session.game_id,
+                session.started_at,
+                session.ended_at,
+                serde_json::to_string(session)?,
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// `base`, numbered when a session with that id exists already
+    fn free_id(conn: &Connection, base: &str) -> Result<String> {
+        let mut id = base.to_string();
+        let mut n = 1;
+        while !Self::query(conn, "id = ?1", [&id])?.is_empty() {
+            n += 1;
+            id = format!("{}-{}", base, n);
+        }
+        Ok(id)
+    }
+
+    /// Id for a new session, `base` numbered when a session with that id exists already
+    pub fn next_id(&self, base: &str) -> Result<String> {
+        self.database().with(|conn| Self::free_id(conn, base))
+    }
+
+    /// Gets all sessions
+    pub fn get_all(&self) -> Result<Sessions> {
+        debug!("Getting all sessions");
+        self.database().with(|conn| Self::query(conn, "1", []))
+    }
+
+    /// Gets a session by id
+    pub fn get(&self, session_id: &str) -> Result<PlaySession> {
+        self.database()
+            .with(|conn| Self::query(conn, "id = ?1", [session_id]))?
+            .pop()
+            .context(format!("Session with id {} not found", session_id))
+    }
+
+    /// Gets all sessions of a single game
+    pub fn get_by_game(&self, game_id: &str) -> Result<Sessions> {
+        debug!("Getting sessions for game: {}", game_id);
+        self.database()
+            .with(|conn| Self::query(conn, "game_id = ?1", [game_id]))
+    }
+
+    /// Gets all sessions overlapping the inclusive `[from, to]` range of Unix timestamps
+    pub fn get_in_range(&self, from: u64, to: u64) -> Result<Sessions> {
+        debug!("Getting sessions between {} and {}", from, to);
+        self.database().with(|conn| {
+            Self::query(
+                conn,
+                "started_at <= ?2 AND COALESCE(ended_at, ?3) >= ?1",
+                (from, to, util::unix_now()),
+            )
+        })
+    }
+
+    /// Opens a new session for a game and returns it
+    pub fn start(
+        &self,
+        game_id: &str,
+        source: TrackingSource,
+        chars_read: u64,
+    ) -> Result<PlaySession> {
+        info!("Starting {:?} session for game: {}", source, game_id);
+        let started_at = util::unix_now();
+        let mut session = PlaySession {
+            id: format!("{}-{}", game_id, started_at),
+            game_id: game_id.to_string(),
+            started_at,
+            ended_at: None,
+            active_secs: 0,
+            chars_read_start: chars_read,
+            chars_read_end: chars_read,
+            source,
+            pauses: Vec::new(),
+            note: None,
+            chars_per_hour: None,
+            first_played_before: None,
+            last_played_before: None,
+        };
+
+        // A game restarted within the same second would otherwise overwrite its last session
+        self.database().with(|conn| {
+            let tx = conn.transaction()?;
+            session.id = Self::free_id(&tx, &session.id)?;
+            Self::write(&tx, &session)?;
+            tx.commit()?;
+            Ok(())
+        })?;
+        Ok(session)
+    }
+
+    /// Adds a new session
+    pub fn insert(&self, session: PlaySession) -> Result<()> {
+        info!("Adding {:?} session: {}", session.source, session.id);
+        self.database().with(|conn| {
+            let tx = conn.transaction()?;
+            if !Self::query(&tx, "id = ?1", [&session.id])?.is_empty() {
+                anyhow::bail!("Session with id {} already exists", session.id);
+            }
+            Self::write(&tx, &session)?;
+            tx.commit()?;
+            Ok(())
+        })
+    }
+
+    /// Replaces an existing session
+    pub fn replace(&self, session: PlaySession) -> Result<()> {
+        debug!("Replacing session with id: {}", session.id);
+        let session_id = session.id.clone();
+        self.update_session(&session_id, |s| *s = session)
+    }
+
+    /// Generic update method for a single session.
+    pub fn update_session<F>(&self, session_id: &str, update_fn: F) -> Result<()>
+    where
+        F: FnOnce(&mut PlaySession),
+    {
+        debug!("Updating session with id: {}", session_id);
+        self.database().with(|conn| {
+            let tx = conn.transaction()?;
+            let mut session = Self::query(&tx, "id = ?1", [session_id])?
+                .pop()
+                .context(format!("Session with id {} not found", session_id))?;
+
+            update_fn(&mut session);
+            Self::write(&tx, &session)?;
+            tx.commit()?;
+            Ok(())
+        })
+    }
+
+    /// Records the latest progress of a running session
+    pub fn record_progress(
+        &self,
+        session_id: &str,
+        active_secs: u64,
+        chars_read: u64,
+    ) -> Result<()> {
+        self.update_session(session_id, |s| {
+            s.active_secs = active_secs;
+            s.chars_read_end = chars_read;
+            s.refresh_speed();
+        })
+    }
+
+    /// Opens a pause interval in a running session
+    pub fn start_pause(&self, session_id: &str, at: u64) -> Result<()> {
+        debug!("Pausing session: {}", session_id);
+        self.update_session(session_id, |s| {
+            if s.pauses.last().is_none_or(|p| p.ended_at.is_some()) {
+                s.pauses.push(PauseInterval {
+                    started_at: at,
+                    ended_at: None,
+                });
+            }
+        })
+    }
+
+    /// Closes the open pause interval of a session, if any
+    pub fn end_pause(&self, session_id: &str, at: u64) -> Result<()> {
+        debug!("Resuming session: {}", session_id);
+        self.update_session(session_id, |s| s.close_pause(at))
+    }
+
+    /// Closes a session with its final totals, `ended_at` is a Unix timestamp in seconds
+    pub fn end(
+        &self,
+        session_id: &str,
+        active_secs: u64,
+        chars_read: u64,
+        ended_at: u64,
+    ) -> Result<()> {
+        info!("Ending session: {}", session_id);
+        self.update_session(session_id, |s| {
+            s.active_secs = active_secs;
+            s.chars_read_end = chars_read;
+            s.ended_at = Some(ended_at);
+            s.close_pause(ended_at);
+            s.refresh_speed();
+        })
+    }
+
+    /// Deletes a single session. Aggregated game stats are left untouched.
+    pub fn delete(&self, session_id: &str) -> Result<()> {
+        info!("Deleting session: {}", session_id);
+        let deleted = self
+            .database()
+            .with(|conn| Ok(conn.execute("DELETE FROM sessions WHERE id = ?1", [session_id])?))?;
+
+        if deleted == 0 {
+            anyhow::bail!("Session with id {} not found", session_id);
+        }
+        Ok(())
+    }
+
+    /// Deletes every session belonging to a game
+    pub fn delete_by_game(&self, game_id: &str) -> Result<()> {
+        info!("Deleting sessions for game: {}", game_id);
+        self.database().with(|conn| {
+            conn.execute("DELETE FROM sessions WHERE game_id = ?1", [game_id])?;
+            Ok(())
+        })
+    }
+}
@@ -1,8 +1,14 @@
-use crate::{prelude::*, services::discord::DiscordPresenceMode};
+use crate::{
+    prelude::*,
+    services::{
+        discord::DiscordPresenceMode,
+        stores::file::{SETTINGS_FILE, StoreFile},
+    },
+    util::DayBoundary,
+};
 use log::{debug, info};
 use serde::{Deserialize, Serialize};
 use tauri::AppHandle;
-use tauri_plugin_store::StoreExt;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ThemeSettings {
@@ -38,6 +44,69 @@ pub enum PlaytimeMode {
     ExStatic,
 }
 
+/// Which of several running games accrue playtime
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConcurrentPlaytimePolicy {
+    /// Only the game owning the focused window (Windows only, other platforms count every game)
+    #[default]
+    FocusedOnly,
+    /// Every running game accrues playtime
+    AllRunning,
+}
+
+/// What a reading goal counts
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalMetric {
+    /// Seconds of playtime
+    Playtime,
+    Chars,
+}
+
+/// Span of reading days a goal has to be met in
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalPeriod {
+    Daily,
+    /// Monday to Sunday
+    Weekly,
+    Monthly,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReadingGoal {
+    pub id: String,
+    pub metric: GoalMetric,
+    pub period: GoalPeriod,
+    /// Seconds or characters, depending on `metric`
+    pub target: u64,
+    /// Only games in this category count, every game when `None`
+    #[serde(default)]
+    pub category: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct BackupSettings {
+    /// Hours between scheduled backups, 0 disables them
+    pub interval_hours: u64,
+    /// Backups kept for each reason, older ones are deleted
+    pub keep: usize,
+    /// Also copy the cover images, which makes backups much larger
+    pub include_images: bool,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            interval_hours: 24,
+            keep: 7,
+            include_images: false,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Settings {
     pub disable_presence_on_nsfw: bool,
@@ -49,6 +118,29 @@ pub struct Settings {
     pub discord_presence_mode: DiscordPresenceMode,
     pub hide_nsfw_images: bool,
     pub jiten_base_url: String,
+    /// Hour (0-23) at which a new reading day begins
+    pub day_start_hour: u8,
+    /// Fixed offset from UTC in minutes used for reading days, `None` follows the system timezone
+    pub utc_offset_minutes: Option<i32>,
+    /// Start tracking library games that were launched outside Tadoku
+    pub auto_detect_games: bool,
+    pub concurrent_playtime_policy: ConcurrentPlaytimePolicy,
+    /// Seconds to look for a game's process after launching it
+    pub process_discovery_timeout_secs: u64,
+    /// Minutes without text or window activity after which Classic tracking stops
+    /// and the idle time is taken back, 0 disables idle detection
+    pub idle_timeout_mins: u64,
+    pub reading_goals: Vec<ReadingGoal>,
+    pub backup_settings: BackupSettings,
+}
+
+impl Settings {
+    pub fn day_boundary(&self) -> DayBoundary {
+        DayBoundary {
+            start_hour: self.day_start_hour,
+            utc_offset_minutes: self.utc_offset_minutes,
+        }
+    }
 }
 
 impl Default for Settings {
@@ -63,6 +155,14 @@ impl Default for Settings {
             discord_presence_mode: DiscordPresenceMode::default(),
             hide_nsfw_images: false,
             jiten_base_url: "https://api.jiten.moe".to_string(),
+            day_start_hour: 0,
+            utc_offset_minutes: None,
+            auto_detect_games: true,
+            concurrent_playtime_policy: ConcurrentPlaytimePolicy::default(),
+            process_discovery_timeout_secs: 60,
+            idle_timeout_mins: 0,
+            reading_goals: Vec::new(),
+            backup_settings: BackupSettings::default(),
         }
     }
 }
@@ -82,7 +182,7 @@ impl SettingsStore {
     /// Since the store is flat, we reconstruct the struct from individual keys.
     pub fn load(&self) -> Result<Settings> {
         debug!("Loading settings from store");
-        let store = self.app_handle.store("settings.json")?;
+        let store = StoreFile::open(&self.app_handle, SETTINGS_FILE)?;
 
         let default_settings = Settings::default();
         let default_value = serde_json::to_value(&default_settings)?;
@@ -107,7 +207,7 @@ impl SettingsStore {
     /// Saves settings to the store as a flat structure.
     pub fn save(&self, settings: &Settings) -> Result<()> {
         info!("Saving settings to store");
-        let store = self.app_handle.store("settings.json")?;
+        let store = StoreFile::open(&self.app_handle, SETTINGS_FILE)?;
         let value = serde_json::to_value(settings)?;
 
         if let Some(obj) = value.as_object() {
@@ -1,7 +1,19 @@
-use crate::prelude::Result;
-use log::debug;
+use crate::{
+    prelude::Result,
+    services::stores::games::{Game, Games, ProcessMatcher},
+};
+use anyhow::Context;
+use log::{debug, info, warn};
+use regex::Regex;
 use serde::Serialize;
-use sysinfo::{Pid, ProcessRefreshKind, RefreshKind, System};
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex, MutexGuard},
+    time::Duration,
+};
+use sysinfo::{Pid, Process, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+use tokio_util::sync::CancellationToken;
 
 #[derive(Serialize)]
 pub struct ActiveWindow {
@@ -10,6 +22,100 @@ pub struct ActiveWindow {
     pub icon: Option<String>,
 }
 
+/// A running process accepted by a `ProcessMatcher`
+#[derive(Serialize)]
+pub struct MatchedProcess {
+    pub pid: u32,
+    pub name: String,
+    pub exe: Option<String>,
+    pub cmdline: String,
+}
+
+/// Maximum number of ancestors walked when following a launcher's process tree
+const MAX_TREE_DEPTH: usize = 16;
+/// Time between scheduled refreshes of the process index
+const INDEX_REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+static PROCESS_INDEX: LazyLock<ProcessIndex> = LazyLock::new(ProcessIndex::new);
+
+/// Long-lived list of running processes shared by every tracker.
+/// It's refreshed on a schedule with only the details process matching needs,
+/// so queries never pay for a full process scan.
+pub struct ProcessIndex {
+    system: Mutex<System>,
+}
+
+impl ProcessIndex {
+    fn new() -> Self {
+        let mut system = System::new();
+        Self::refresh_system(&mut system, ProcessesToUpdate::All);
+        Self {
+            system: Mutex::new(system),
+        }
+    }
+
+    pub fn global() -> &'static Self {
+        &PROCESS_INDEX
+    }
+
+    /// Spawns the task refreshing the index until shutdown
+    pub fn spawn(token: CancellationToken) {
+        info!("Spawning process index refresh task");
+        tauri::async_runtime::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = token.cancelled() => {
+                        info!("Shutdown signal received, stopping process index refresh.");
+                        break;
+                    }
+                    _ = tokio::time::sleep(INDEX_REFRESH_INTERVAL) => {
+                        let refresh = tauri::async_runtime::spawn_blocking(|| Self::global().refresh());
+                        if let Err(e) = refresh.await {
+                            warn!("Process index refresh failed: {}", e);
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    fn refresh_system(system: &mut System, processes: ProcessesToUpdate) {
+        // Executable and command line don't change for a running process, they're read once
+        system.refresh_processes_specifics(
+            processes,
+            true,
+            ProcessRefreshKind::nothing()
+                .with_exe(UpdateKind::OnlyIfNotSet)
+                .with_cmd(UpdateKind::OnlyIfNotSet),
+        );
+    }
+
+    fn lock(&self) -> MutexGuard<'_, System> {
+        // The process list stays usable even if a query panicked while holding it
+        self.system.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Rescans the running processes
+    pub fn refresh(&self) {
+        Self::refresh_system(&mut self.lock(), ProcessesToUpdate::All);
+    }
+
+    /// Refreshes only the given processes, dropping the ones that exited
+    pub fn refresh_pids(&self, pids: &[Pid]) {
+        Self::refresh_system(&mut self.lock(), ProcessesToUpdate::Some(pids));
+    }
+
+    /// Whether a process was running at the last refresh
+    pub fn is_alive(&self, pid: Pid) -> bool {
+        self.lock().process(pid).is_some()
+    }
+
+    /// Runs a query against the processes seen at the last refresh
+    pub fn with<R>(&self, query: impl FnOnce(&System) -> R) -> R {
+        query(&self.lock())
+    }
+}
+
 pub struct SystemService;
 
 impl SystemService {
@@ -51,35 +157,242 @@ impl SystemService {
         }
     }
 
+    fn cmdline(process: &Process) -> String {
+        process
+            .cmd()
+            .iter()
+            .filter_map(|s| s.to_str())
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
+    /// Whether a running process belongs to a saved game's process file path
+    fn process_matches(process: &Process, process_file_path: &str) -> bool {
+        if process_file_path.is_empty() {
+            return false;
+        }
+
+        let Some(exe) = process.exe() else {
+            return false;
+        };
+
+        if exe.to_str() == Some(process_file_path) {
+            return true;
+        }
+
+        #[cfg(not(windows))]
+        {
+            let normalized_path = Self::cmdline(process).replace("\\", "/");
+
+            if normalized_path.contains(process_file_path) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Directory holding a game's process, `None` for bare file names
+    fn install_dir(process_file_path: &str) -> Option<&Path> {
+        Path::new(process_file_path)
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+    }
+
+    fn is_under(process: &Process, dir: &Path) -> bool {
+        process.exe().is_some_and(|exe| exe.starts_with(dir))
+    }
+
+    /// Whether `pid` is a (transitive) child of `ancestor`
+    fn is_descendant(system: &System, pid: Pid, ancestor: Pid) -> bool {
+        let mut current = system.process(pid).and_then(|p| p.parent());
+
+        // Bounded walk, reused PIDs can make the parent chain loop
+        for _ in 0..MAX_TREE_DEPTH {
+            match current {
+                Some(parent) if parent == ancestor => return true,
+                Some(parent) => current = system.process(parent).and_then(|p| p.parent()),
+                None => return false,
+            }
+        }
+
+        false
+    }
+
+    /// Picks the process to track for a game whose launch spawned `launcher`.
+    /// A process accepted by the game's matcher wins, otherwise the newest descendant
+    /// of the launcher, preferring ones running from the game's install directory.
+    pub fn find_game_process(game: &Game, launcher: Option<Pid>) -> Option<Pid> {
+        let matcher = CompiledMatcher::for_game(game);
+        let install_dir = Self::install_dir(&game.process_file_path);
+
+        ProcessIndex::global().with(|system| {
+            if let Some(process) = system.processes().values().find(|p| matcher.matches(p)) {
+                return Some(process.pid());
+            }
+
+            let launcher = launcher?;
+
+            system
+                .processes()
+                .values()
+                .filter(|p| Self::is_descendant(system, p.pid(), launcher))
+                .max_by_key(|p| {
+                    (
+                        install_dir.is_some_and(|dir| Self::is_under(p, dir)),
+                        p.start_time(),
+                    )
+                })
+                .map(|p| p.pid())
+        })
+    }
+
+    /// Finds a process that can take over tracking after the tracked one exited,
+    /// either one accepted by the game's matcher or any process running from its install directory
+    pub fn find_replacement_process(game: &Game) -> Option<Pid> {
+        if game.process_file_path.is_empty() {
+            return None;
+        }
+
+        let matcher = CompiledMatcher::for_game(game);
+        let install_dir = Self::install_dir(&game.process_file_path);
+
+        ProcessIndex::global().with(|system| {
+            system
+                .processes()
+                .values()
+                .filter(|p| {
+                    matcher.matches(p) || install_dir.is_some_and(|dir| Self::is_under(p, dir))
+                })
+                .max_by_key(|p| (matcher.matches(p), p.start_time()))
+                .map(|p| p.pid())
+        })
+    }
+
     /// Gets the PID of a saved game's process file path
     pub fn get_pid_from_process_path(process_file_path: &str) -> Option<Pid> {
-        let s = System::new_with_specifics(
-            RefreshKind::nothing().with_processes(ProcessRefreshKind::everything()),
-        );
+        ProcessIndex::global().with(|system| {
+            system
+                .processes()
+                .values()
+                .find(|p| Self::process_matches(p, process_file_path))
+                .map(|p| p.pid())
+        })
+    }
 
-        for process in s.processes().values() {
-            if let Some(exe) = process.exe() {
-                if exe.to_str()? == process_file_path {
-                    return Some(process.pid());
-                }
+    /// Finds the first saved game with a running process, returning its id and PID
+    pub fn find_running_game(games: &Games) -> Option<(String, Pid)> {
+        let matchers: Vec<(&String, CompiledMatcher)> = games
+            .iter()
+            .filter(|(_, game)| !game.process_file_path.is_empty())
+            .map(|(id, game)| (id, CompiledMatcher::for_game(game)))
+            .collect();
 
-                #[cfg(not(windows))]
-                {
-                    let normalized_path = process
-                        .cmd()
-                        .iter()
-                        .filter_map(|s| s.to_str())
-                        .collect::<Vec<&str>>()
-                        .join(" ")
-                        .replace("\\", "/");
-
-                    if normalized_path.contains(process_file_path) {
-                        return Some(process.pid());
-                    }
-                }
+        ProcessIndex::global().with(|system| {
+            matchers.iter().find_map(|(id, matcher)| {
+                system
+                    .processes()
+                    .values()
+                    .find(|p| matcher.matches(p))
+                    .map(|p| ((*id).clone(), p.pid()))
+            })
+        })
+    }
+
+    /// Lists the running processes accepted by a matcher
+    pub fn list_matching_processes(matcher: &CompiledMatcher) -> Vec<MatchedProcess> {
+        ProcessIndex::global().with(|system| {
+            system
+                .processes()
+                .values()
+                // Linux lists threads as processes too
+                .filter(|p| p.thread_kind().is_none() && matcher.matches(p))
+                .map(|p| MatchedProcess {
+                    pid: p.pid().as_u32(),
+                    name: p.name().to_string_lossy().into_owned(),
+                    exe: p.exe().map(|e| e.to_string_lossy().into_owned()),
+                    cmdline: Self::cmdline(p),
+                })
+                .collect()
+        })
+    }
+}
+
+enum MatcherKind {
+    ExactPath,
+    FileName(OsString),
+    PathGlob(glob::Pattern),
+    CmdlineRegex(Regex),
+    InstallDir(PathBuf),
+}
+
+/// A `ProcessMatcher` ready to be tested against running processes
+pub struct CompiledMatcher {
+    kind: MatcherKind,
+    process_file_path: String,
+}
+
+impl CompiledMatcher {
+    pub fn new(matcher: &ProcessMatcher, process_file_path: &str) -> Result<Self> {
+        let kind = match matcher {
+            ProcessMatcher::ExactPath => MatcherKind::ExactPath,
+            ProcessMatcher::FileName => MatcherKind::FileName(
+                Path::new(process_file_path)
+                    .file_name()
+                    .context("Process file path has no file name")?
+                    .to_owned(),
+            ),
+            ProcessMatcher::PathGlob(pattern) => MatcherKind::PathGlob(
+                glob::Pattern::new(pattern)
+                    .context(format!("Invalid glob pattern: {}", pattern))?,
+            ),
+            ProcessMatcher::CmdlineRegex(pattern) => MatcherKind::CmdlineRegex(
+                Regex::new(pattern).context(format!("Invalid regex: {}", pattern))?,
+            ),
+            ProcessMatcher::InstallDir(Some(dir)) => MatcherKind::InstallDir(PathBuf::from(dir)),
+            ProcessMatcher::InstallDir(None) => MatcherKind::InstallDir(
+                SystemService::install_dir(process_file_path)
+                    .context("Process file path has no parent directory")?
+                    .to_path_buf(),
+            ),
+        };
+
+        Ok(Self {
+            kind,
+            process_file_path: process_file_path.to_string(),
+        })
+    }
+
+    /// Compiles a game's matcher, falling back to exact path matching when it's invalid
+    pub fn for_game(game: &Game) -> Self {
+        Self::new(&game.process_matcher, &game.process_file_path).unwrap_or_else(|e| {
+            warn!("Invalid process matcher, using exact path: {:#}", e);
+            Self {
+                kind: MatcherKind::ExactPath,
+                process_file_path: game.process_file_path.clone(),
             }
-        }
+        })
+    }
 
-        None
+    pub fn matches(&self, process: &Process) -> bool {
+        match &self.kind {
+            MatcherKind::ExactPath => {
+                SystemService::process_matches(process, &self.process_file_path)
+            }
+            MatcherKind::FileName(name) => {
+                process.exe().and_then(|exe| exe.file_name()) == Some(name.as_os_str())
+            }
+            MatcherKind::PathGlob(pattern) => process.exe().is_some_and(|exe| {
+                pattern.matches_path_with(
+                    exe,
+                    glob::MatchOptions {
+                        case_sensitive: !cfg!(windows),
+                        ..glob::MatchOptions::new()
+                    },
+                )
+            }),
+            MatcherKind::CmdlineRegex(regex) => regex.is_match(&SystemService::cmdline(process)),
+            MatcherKind::InstallDir(dir) => SystemService::is_under(process, dir),
+        }
     }
 }
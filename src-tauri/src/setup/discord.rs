@@ -1,10 +1,8 @@
-use crate::services::state::ManagedState;
 use crate::services::discord::DiscordPresence;
+use crate::services::state::ManagedState;
 use log::{debug, error, info, warn};
 use tauri::{AppHandle, Manager};
 
-
-
 pub fn initialize(app_handle: &AppHandle) -> tauri::Result<()> {
     info!("Initializing Discord presence");
     let app_handle_clone = app_handle.clone();
@@ -52,4 +50,4 @@ pub fn initialize(app_handle: &AppHandle) -> tauri::Result<()> {
 
     info!("Discord initialization task spawned");
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,89 @@
+use crate::prelude::Result;
+use crate::services::journal::{JournalEntry, SessionJournal};
+use crate::services::state::ManagedState;
+use crate::services::stores::{games::GamesStore, sessions::SessionsStore};
+use crate::util::DayBoundary;
+use anyhow::Context;
+use log::{error, info, warn};
+use tauri::{AppHandle, Manager};
+
+/// Replays the session journal left behind by a run that didn't close its sessions,
+/// crediting the untracked playtime and closing the sessions
+pub fn recover(app_handle: &AppHandle) -> Result<()> {
+    let journal = SessionJournal::new(app_handle)?;
+
+    let entries = match journal.read() {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Discarding unreadable session journal: {:?}", e);
+            return journal.clear();
+        }
+    };
+
+    if entries.is_empty() {
+        return Ok(());
+    }
+
+    let day = app_handle
+        .state::<ManagedState>()
+        .lock()?
+        .settings
+        .day_boundary();
+    let store = GamesStore::new(app_handle).context("Failed to access games store")?;
+
+    for entry in entries {
+        if let Err(e) = recover_entry(app_handle, &store, &day, &entry) {
+            error!(
+                "Failed to recover interrupted session for game {}: {:?}",
+                entry.game_id, e
+            );
+        }
+    }
+
+    journal.clear()
+}
+
+fn recover_entry(
+    app_handle: &AppHandle,
+    store: &GamesStore,
+    day: &DayBoundary,
+    entry: &JournalEntry,
+) -> Result<()> {
+    info!(
+        "Recovering interrupted session for game {} ({}s unflushed)",
+        entry.game_id,
+        entry.unflushed_playtime()
+    );
+
+    let Some(game) = store.get(&entry.game_id) else {
+        warn!(
+            "Game {} from session journal no longer exists, discarding",
+            entry.game_id
+        );
+        return Ok(());
+    };
+
+    let date = day.date_of(entry.updated_at);
+    store
+        .update_playtime_on(&entry.game_id, entry.unflushed_playtime(), &date)
+        .context("Failed to credit recovered playtime")?;
+    store
+        .update_game(&entry.game_id, |g| g.last_played = Some(entry.updated_at))
+        .context("Failed to update last played")?;
+
+    if let Some(session_id) = &entry.session_id {
+        SessionsStore::new(app_handle)
+            .and_then(|sessions| {
+                sessions.end(
+                    session_id,
+                    entry.current_playtime,
+                    game.chars_read,
+                    entry.updated_at,
+                )
+            })
+            .context(format!("Failed to close recovered session {}", session_id))?;
+    }
+
+    info!("Recovered interrupted session for game {}", entry.game_id);
+    Ok(())
+}
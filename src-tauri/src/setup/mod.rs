@@ -1,16 +1,48 @@
 use crate::prelude::Result;
-use tauri::AppHandle;
+use crate::services::{
+    goals::GoalTracker,
+    state::ManagedState,
+    stores::{database::Database, file::StoreRecovery, games::GamesRepository},
+};
+use anyhow::Context;
+use log::error;
+use tauri::{AppHandle, Emitter, Manager};
 
 mod background;
 mod discord;
 mod images;
+mod journal;
 mod state;
-mod store;
+pub mod store;
 
 pub fn run(app: &AppHandle) -> Result<()> {
-    store::migrate(app)?;
+    let corrupt_stores = StoreRecovery::new(app)
+        .check()
+        .context("Failed to check store files")?;
+    app.manage(Database::open(app).context("Failed to open database")?);
+    let store_notice = store::migrate(app)?;
+    let repository = GamesRepository::load(&app.state::<Database>())?;
+    app.manage(repository);
     images::ensure_folder(app)?;
     state::initialize(app)?;
+    if !corrupt_stores.is_empty() {
+        if let Err(e) = app.emit("store_corrupted", &corrupt_stores) {
+            error!("Failed to emit store_corrupted event: {}", e);
+        }
+        app.state::<ManagedState>().lock()?.corrupt_stores = corrupt_stores;
+    }
+    if let Some(notice) = store_notice {
+        if let Err(e) = app.emit("store_notice", &notice) {
+            error!("Failed to emit store_notice event: {}", e);
+        }
+        app.state::<ManagedState>().lock()?.store_notice = Some(notice);
+    }
+    if let Err(e) = journal::recover(app) {
+        error!("Failed to recover interrupted session: {:?}", e);
+    }
+    if let Err(e) = GoalTracker::seed(app) {
+        error!("Failed to evaluate reading goals: {:?}", e);
+    }
     let _ = discord::initialize(app); // intentionally infallible at the top level
     background::spawn(app);
     Ok(())
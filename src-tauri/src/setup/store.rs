@@ -1,29 +1,56 @@
-use crate::prelude::{Fetchable, Result, Store};
-use crate::services::stores::games::Game;
+use crate::prelude::{Fetchable, Result};
+use crate::services::backup::{BackupReason, Backups};
+use crate::services::stores::{
+    audit::{AuditEntry, AuditKind, AuditStore},
+    categories::Categories,
+    database::Database,
+    file::{STORE_FILE, StoreFile},
+    games::{Game, Games},
+    sessions::Sessions,
+};
+use crate::util;
 use anyhow::Context;
 use log::{debug, info, warn};
-use tauri::AppHandle;
-use tauri_plugin_store::StoreExt;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use tauri::{AppHandle, Manager};
 
-const CURRENT_VERSION: u32 = 1;
+const CURRENT_VERSION: u32 = 3;
 const VERSION_KEY: &str = "schemaVersion";
+/// Keys of the data moved into the database by v2 -> v3
+const IMPORTED_KEYS: [&str; 4] = ["gamesData", "sessions", "categories", "selected_categories"];
 
-pub fn migrate(app_handle: &AppHandle) -> Result<()> {
-    let store = app_handle
-        .store("store.json")
-        .context("Failed to access store.json")?;
+/// Brings `store.json` up to the current schema. Returns a notice for the user when it holds
+/// a library that was left in place because the database has one already.
+pub fn migrate(app_handle: &AppHandle) -> Result<Option<String>> {
+    let store = StoreFile::open(app_handle, STORE_FILE).context("Failed to access store.json")?;
 
     let version = read_version(&store);
 
     if version < CURRENT_VERSION {
-        run_migrations(&store, version)?;
+        // A new install has nothing to lose
+        if !store.is_empty() {
+            Backups::new(app_handle)
+                .create(BackupReason::Migration)
+                .context("Failed to back up before migrating store")?;
+        }
+        let audit = AuditStore::new(app_handle).context("Failed to access audit log")?;
+        let database = app_handle.state::<Database>();
+        run_migrations(&store, &audit, &database, version)?;
         write_version(&store, CURRENT_VERSION)?;
     }
 
-    Ok(())
+    if !store.has("gamesData") {
+        return Ok(None);
+    }
+    Ok(Some(
+        "store.json holds a library from an older version, but the database has one already. \
+         The library in the database was left unchanged, the older one stays in store.json."
+            .to_string(),
+    ))
 }
 
-fn read_version(store: &Store) -> u32 {
+fn read_version(store: &StoreFile) -> u32 {
     store
         .get(VERSION_KEY)
         .and_then(|v| v.as_u64())
@@ -31,21 +58,80 @@ fn read_version(store: &Store) -> u32 {
         .unwrap_or(0) // no key = version 0 = existing users
 }
 
-fn write_version(store: &Store, version: u32) -> Result<()> {
+fn write_version(store: &StoreFile, version: u32) -> Result<()> {
     store.set(VERSION_KEY, version);
     store.save().context("Failed to save schema version")
 }
 
-fn run_migrations(store: &Store, from: u32) -> Result<()> {
-    if from < 1 {
-        v0_to_v1(store)?;
+fn run_migrations(
+    store: &StoreFile,
+    audit: &AuditStore,
+    database: &Database,
+    from: u32,
+) -> Result<()> {
+    let import = |store: &StoreFile| v2_to_v3(store, database);
+    let migrations: [(u32, &dyn Fn(&StoreFile) -> Result<()>); 3] =
+        [(1, &v0_to_v1), (2, &v1_to_v2), (3, &import)];
+
+    for (version, migration) in migrations {
+        if from < version {
+            let before = read_totals(store);
+            migration(store)?;
+            audit_totals(store, audit, &before, version)?;
+        }
     }
-    // if from < 2 { v1_to_v2(app_handle, store)?; }
     Ok(())
 }
 
+/// `playtime` and `chars_read` of every game, read straight from the stored JSON
+fn read_totals(store: &StoreFile) -> HashMap<String, (u64, u64)> {
+    let Some(games) = store.get("gamesData") else {
+        return HashMap::new();
+    };
+    let Some(games) = games.as_object() else {
+        return HashMap::new();
+    };
+
+    games
+        .iter()
+        .map(|(game_id, game)| {
+            let field = |key: &str| game.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+            (game_id.clone(), (field("playtime"), field("chars_read")))
+        })
+        .collect()
+}
+
+/// Records every game whose totals a migration changed in the audit log
+fn audit_totals(
+    store: &StoreFile,
+    audit: &AuditStore,
+    before: &HashMap<String, (u64, u64)>,
+    version: u32,
+) -> Result<()> {
+    let entries: Vec<AuditEntry> = read_totals(store)
+        .into_iter()
+        .filter_map(|(game_id, after)| {
+            let before = before.get(&game_id).copied()?;
+            (before != after).then(|| AuditEntry {
+                game_id,
+                at: util::unix_now(),
+                kind: AuditKind::Migration,
+                playtime_before: before.0,
+                playtime_after: after.0,
+                chars_read_before: before.1,
+                chars_read_after: after.1,
+                reason: Some(format!("Migration v{} -> v{}", version - 1, version)),
+            })
+        })
+        .collect();
+
+    audit
+        .record(entries)
+        .context("Failed to record migration changes in audit log")
+}
+
 /// Your existing migration logic, verbatim
-fn v0_to_v1(store: &Store) -> Result<()> {
+fn v0_to_v1(store: &StoreFile) -> Result<()> {
     info!("Running migration v0 -> v1");
 
     let mut binding = match store.get("gamesData") {
@@ -152,3 +238,112 @@ fn v0_to_v1(store: &Store) -> Result<()> {
     info!("Migration v0 -> v1 complete");
     Ok(())
 }
+
+/// Seeds the per-day ledger from `today_playtime` / `last_play_date`, the only
+/// daily figure kept before the ledger existed
+fn v1_to_v2(store: &StoreFile) -> Result<()> {
+    info!("Running migration v1 -> v2");
+
+    let mut binding = match store.get("gamesData") {
+        Some(data) => data.clone(),
+        None => {
+            info!("No gamesData found, skipping v1->v2 migration");
+            return Ok(());
+        }
+    };
+
+    let games = binding
+        .as_object_mut()
+        .context("Failed to get gamesData as an object from store")?;
+
+    let mut seeded_games = 0;
+
+    for (game_id, game_value) in games.iter_mut() {
+        let game = game_value
+            .as_object_mut()
+            .context(format!("Failed to get game {} as an object", game_id))?;
+
+        let today_playtime = game
+            .get("today_playtime")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0);
+        let last_play_date = game
+            .get("last_play_date")
+            .and_then(|v| v.as_str())
+            .map(str::to_owned);
+
+        let ledger = game
+            .entry("daily_playtime")
+            .or_insert_with(|| serde_json::json!({}));
+
+        // Also replaces a `null` ledger
+        if !ledger.is_object() {
+            *ledger = serde_json::json!({});
+        }
+
+        if let (Some(date), true) = (last_play_date, today_playtime > 0) {
+            let ledger = ledger.as_object_mut().expect("checked above");
+            if !ledger.contains_key(&date) {
+                debug!(
+                    "Seeding daily playtime for game {}: {} -> {}s",
+                    game_id, date, today_playtime
+                );
+                ledger.insert(date, today_playtime.into());
+                seeded_games += 1;
+            }
+        }
+
+        game.entry("daily_chars")
+            .or_insert_with(|| serde_json::json!({}));
+    }
+
+    store.set("gamesData", binding);
+    store
+        .save()
+        .context("Failed to save updated games data to store")?;
+
+    info!(
+        "Migration v1 -> v2 complete: seeded daily playtime for {} games",
+        seeded_games
+    );
+    Ok(())
+}
+
+/// Moves the library, categories and sessions out of `store.json` into the SQLite database
+fn v2_to_v3(store: &StoreFile, database: &Database) -> Result<()> {
+    info!("Running migration v2 -> v3");
+
+    let games: Games = read_json(store, "gamesData")?;
+    let sessions: Sessions = read_json(store, "sessions")?;
+    let categories: Categories = read_json(store, "categories")?;
+    let selected_categories: Categories = read_json(store, "selected_categories")?;
+
+    let imported = database
+        .import(&games, &sessions, &categories, &selected_categories)
+        .context("Failed to import store into database")?;
+    if !imported {
+        // Deleting the data would lose it, the database already holds another library
+        warn!("The database holds an imported library already, leaving store.json's in place");
+        return Ok(());
+    }
+
+    for key in IMPORTED_KEYS {
+        store.delete(key);
+    }
+    store
+        .save()
+        .context("Failed to remove imported data from store")?;
+
+    info!("Migration v2 -> v3 complete");
+    Ok(())
+}
+
+/// A value straight from the store, its default when the key is missing
+fn read_json<T: DeserializeOwned + Default>(store: &StoreFile, key: &str) -> Result<T> {
+    store
+        .get(key)
+        .map(serde_json::from_value)
+        .transpose()
+        .context(format!("Failed to read {} from store", key))
+        .map(Option::unwrap_or_default)
+}
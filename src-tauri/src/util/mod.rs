@@ -1,4 +1,8 @@
 mod debug;
 pub mod image;
+mod speed;
+mod time;
 
 pub use debug::*;
+pub use speed::*;
+pub use time::*;
@@ -0,0 +1,8 @@
+/// Reading speed in characters per hour, `None` without both characters and time to go on
+pub fn chars_per_hour(chars: u64, secs: u64) -> Option<u64> {
+    if chars == 0 || secs == 0 {
+        return None;
+    }
+
+    Some(chars * 3600 / secs)
+}
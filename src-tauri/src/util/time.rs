@@ -0,0 +1,75 @@
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeDelta, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::time;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Returns the current Unix timestamp in seconds
+pub fn unix_now() -> u64 {
+    time::SystemTime::now()
+        .duration_since(time::UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
+
+/// Decides which "reading day" a moment belongs to.
+/// A day starts at `start_hour` instead of midnight, so a late-night session
+/// is counted towards the day it started on.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DayBoundary {
+    /// Hour (0-23) at which a new reading day begins
+    pub start_hour: u8,
+    /// Fixed offset from UTC in minutes, the system timezone is used when `None`
+    pub utc_offset_minutes: Option<i32>,
+}
+
+impl DayBoundary {
+    fn local_datetime(&self, timestamp: u64) -> NaiveDateTime {
+        let utc = DateTime::<Utc>::from_timestamp(timestamp as i64, 0).unwrap_or_default();
+
+        match self
+            .utc_offset_minutes
+            .and_then(|m| FixedOffset::east_opt(m * 60))
+        {
+            Some(offset) => utc.with_timezone(&offset).naive_local(),
+            None => utc.with_timezone(&Local).naive_local(),
+        }
+    }
+
+    /// Reading day a Unix timestamp (seconds) belongs to
+    pub fn day_of(&self, timestamp: u64) -> NaiveDate {
+        (self.local_datetime(timestamp) - TimeDelta::hours(self.start_hour.into())).date()
+    }
+
+    /// Reading day a Unix timestamp (seconds) belongs to, formatted as `YYYY-MM-DD`
+    pub fn date_of(&self, timestamp: u64) -> String {
+        self.day_of(timestamp).format(DATE_FORMAT).to_string()
+    }
+
+    /// Current reading day formatted as `YYYY-MM-DD`
+    pub fn today(&self) -> String {
+        self.date_of(unix_now())
+    }
+
+    /// Unix timestamp (seconds) at which a reading day begins. A start skipped by a DST change
+    /// is taken as UTC, off by at most the UTC offset.
+    pub fn start_of(&self, date: NaiveDate) -> u64 {
+        let start = date
+            .and_hms_opt(self.start_hour.into(), 0, 0)
+            .unwrap_or_default();
+
+        let timestamp = match self
+            .utc_offset_minutes
+            .and_then(|m| FixedOffset::east_opt(m * 60))
+        {
+            Some(offset) => offset.from_local_datetime(&start).earliest(),
+            None => Local
+                .from_local_datetime(&start)
+                .earliest()
+                .map(|t| t.fixed_offset()),
+        };
+        timestamp
+            .map_or_else(|| start.and_utc().timestamp(), |t| t.timestamp())
+            .max(0) as u64
+    }
+}
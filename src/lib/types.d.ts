@@ -47,6 +47,103 @@ export interface Game extends GameDto {
   chars_read: number;
   /** Total character count from Jiten API (pre-fetched at startup) */
   jiten_char_count: Fetchable<number>;
+  /** Reading progress, only present when the Jiten character count is known */
+  progress?: Progress;
+}
+
+export interface Progress {
+  /** Share of the game read, 0-100 */
+  percent: number;
+  remaining_chars: number;
+  chars_per_hour: number | null;
+  /** Estimated hours left at `chars_per_hour` */
+  eta_hours: number | null;
+}
+
+export interface ReadingGoal {
+  id: string;
+  metric: 'playtime' | 'chars';
+  period: 'daily' | 'weekly' | 'monthly';
+  /** Seconds or characters, depending on `metric` */
+  target: number;
+  /** Only games in this category count */
+  category?: string | null;
+}
+
+export interface GoalProgress {
+  goal_id: string;
+  current: number;
+  target: number;
+  percent: number;
+  reached: boolean;
+  /** First reading day (YYYY-MM-DD) of the current period */
+  period_start: string;
+}
+
+/** A store file found unreadable on startup, payload of `store_corrupted` */
+export interface CorruptStore {
+  file: string;
+  /** Where the unreadable file was moved to */
+  moved_to: string;
+  /** A known-good copy is available for `restore_store` */
+  restorable: boolean;
+}
+
+export interface BackupSettings {
+  /** Hours between scheduled backups, 0 disables them */
+  interval_hours: number;
+  /** Backups kept for each reason */
+  keep: number;
+  include_images: boolean;
+}
+
+export interface BackupInfo {
+  /** Archive file name, passed to `restore_backup` */
+  name: string;
+  size_bytes: number;
+  /** Unix timestamp (seconds) */
+  created_at: number;
+  reason: 'scheduled' | 'migration' | 'restore' | 'import';
+  app_version: string;
+  includes_images: boolean;
+}
+
+export interface LibraryManifest {
+  archive_version: number;
+  /** Unix timestamp (seconds) */
+  exported_at: number;
+  app_version: string;
+  games: number;
+  sessions: number;
+  images: number;
+}
+
+export interface ImportOptions {
+  /** `merge` keeps games already in the library, `replace` takes the archive's library */
+  mode: 'merge' | 'replace';
+  /** Path prefixes to rewrite, e.g. `D:\Games` to `E:\VN` */
+  path_remaps?: { from: string; to: string }[];
+  import_settings?: boolean;
+}
+
+export interface ImportConflict {
+  game_id: string;
+  library_title: string;
+  archive_title: string;
+  library_playtime: number;
+  archive_playtime: number;
+}
+
+export interface ImportPreview {
+  archive: LibraryManifest;
+  new_games: number;
+  conflicts: ImportConflict[];
+  /** Library games deleted when replacing */
+  removed_games: number;
+  sessions: number;
+  remapped_paths: number;
+  images: number;
+  settings: boolean;
 }
 
 export interface Options {
@@ -55,6 +152,9 @@ export interface Options {
 
 export interface CurrentGame {
   id: string;
+  status: 'playing';
+  /** PID of the tracked process */
+  pid: number;
 }
 
 export interface ProcessItem {